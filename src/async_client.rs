@@ -0,0 +1,325 @@
+use std::io;
+
+#[cfg(target_family = "unix")]
+use std::net::Shutdown;
+
+#[cfg(target_family = "unix")]
+use tokio::net::{UnixDatagram, UnixStream};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpStream, UdpSocket},
+};
+use url::Url;
+
+use crate::{
+    format::{self, Format, Serializer},
+    pipeline::Pipeline,
+    Clock, FieldOrder, LineEncoder, Metric, Point, PointRef, Precision, TelegrafError,
+    TelegrafResult, UnsignedMode,
+};
+
+/// Asynchronous version of [crate::Client] for tokio applications,
+/// available with the `tokio` feature.
+///
+/// Supports the `tcp://`, `udp://`, `statsd://`, `unix://` and
+/// `unixgram://` schemes, and validates and encodes points exactly
+/// like [crate::Client]. Points are encoded before anything is awaited,
+/// so only the write to the socket happens asynchronously.
+///
+/// # Examples
+///
+/// ```no_run
+/// use telegraf::{point, AsyncClient};
+///
+/// # async fn run() -> Result<(), telegraf::TelegrafError> {
+/// let mut client = AsyncClient::new("tcp://localhost:8094").await?;
+/// client.write_point(&point!("cpu", ("load", 0.5))).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncClient {
+    conn: AsyncConnector,
+    pipeline: Pipeline,
+}
+
+/// Connections supported by [AsyncClient].
+enum AsyncConnector {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+    #[cfg(target_family = "unix")]
+    Unix(UnixStream),
+    #[cfg(target_family = "unix")]
+    Unixgram(UnixDatagram),
+}
+
+impl AsyncClient {
+    /// Creates a new client. Determines socket protocol from
+    /// provided URL.
+    pub async fn new(conn_url: &str) -> Result<Self, TelegrafError> {
+        let u = Url::parse(conn_url).map_err(|_| {
+            TelegrafError::BadProtocol(format!("invalid connection URL {}", conn_url))
+        })?;
+        let client = Self {
+            conn: AsyncConnector::new(&u).await?,
+            pipeline: Pipeline::new(LineEncoder::new()),
+        };
+        match u.scheme() {
+            "statsd" => Ok(client.with_format(Format::Statsd(format::Statsd::new()))),
+            _ => Ok(client),
+        }
+    }
+
+    /// See [crate::Client::with_precision].
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.pipeline.encoder = self.pipeline.encoder.with_precision(precision);
+        self
+    }
+
+    /// See [crate::Client::with_unsigned_mode].
+    pub fn with_unsigned_mode(mut self, mode: UnsignedMode) -> Self {
+        self.pipeline.encoder = self.pipeline.encoder.with_unsigned_mode(mode);
+        self
+    }
+
+    /// See [crate::Client::with_format].
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.pipeline.serializer = match format {
+            Format::LineProtocol => None,
+            format => Some(Box::new(format)),
+        };
+        self
+    }
+
+    /// See [crate::Client::with_serializer].
+    pub fn with_serializer<S: Serializer + Send + 'static>(mut self, serializer: S) -> Self {
+        self.pipeline.serializer = Some(Box::new(serializer));
+        self
+    }
+
+    /// See [crate::Client::with_field_order].
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.pipeline.encoder = self.pipeline.encoder.with_field_order(order);
+        self
+    }
+
+    /// See [crate::Client::with_auto_timestamp].
+    pub fn with_auto_timestamp(mut self, precision: Precision) -> Self {
+        self.pipeline.auto_timestamp = Some(precision);
+        self
+    }
+
+    /// See [crate::Client::with_clock].
+    pub fn with_clock<C: Clock + Send + 'static>(mut self, clock: C) -> Self {
+        self.pipeline.clock = Box::new(clock);
+        self
+    }
+
+    /// Writes the protocol representation of a point
+    /// to the established connection.
+    pub async fn write_point(&mut self, pt: &Point) -> TelegrafResult {
+        self.pipeline.encode_point(pt)?;
+        self.write_buf().await
+    }
+
+    /// Joins multiple points together and writes them in a batch,
+    /// like [crate::Client::write_points].
+    pub async fn write_points(&mut self, pts: &[Point]) -> TelegrafResult {
+        self.pipeline.encode_points(pts)?;
+        self.write_buf().await
+    }
+
+    /// Writes a borrowed point to the established connection,
+    /// without converting it to an owned [Point].
+    pub async fn write_point_ref(&mut self, pt: &PointRef<'_>) -> TelegrafResult {
+        self.pipeline.encode_point_ref(pt)?;
+        self.write_buf().await
+    }
+
+    /// Convenience wrapper around writing points for types
+    /// that implement [crate::Metric].
+    pub async fn write<M: Metric>(&mut self, metric: &M) -> TelegrafResult {
+        // Encoded up front so the metric isn't held across the write.
+        metric.with_point_ref(&mut |pt| self.pipeline.encode_point_ref(pt))?;
+        self.write_buf().await
+    }
+
+    /// Closes and cleans up socket connection.
+    pub async fn close(&mut self) -> io::Result<()> {
+        self.conn.close().await
+    }
+
+    /// Writes byte array to internal outgoing socket.
+    pub async fn write_to_conn(&mut self, data: &[u8]) -> TelegrafResult {
+        self.conn.write(data).await
+    }
+
+    /// Writes the encoded contents of the pipeline buffer.
+    async fn write_buf(&mut self) -> TelegrafResult {
+        self.conn.write(&self.pipeline.buf).await
+    }
+}
+
+impl AsyncConnector {
+    async fn new(u: &Url) -> Result<Self, TelegrafError> {
+        match u.scheme() {
+            "tcp" => Ok(Self::Tcp(TcpStream::connect(host_port(u)?).await?)),
+            "udp" | "statsd" => {
+                let conn = UdpSocket::bind("0.0.0.0:0").await?;
+                conn.connect(host_port(u)?).await?;
+                Ok(Self::Udp(conn))
+            }
+            #[cfg(target_family = "unix")]
+            "unix" => Ok(Self::Unix(UnixStream::connect(u.path()).await?)),
+            #[cfg(target_family = "unix")]
+            "unixgram" => {
+                let conn = UnixDatagram::unbound()?;
+                conn.connect(u.path())?;
+                Ok(Self::Unixgram(conn))
+            }
+            scheme @ ("http" | "https" | "tls") => Err(TelegrafError::BadProtocol(format!(
+                "{} connections are not supported by AsyncClient",
+                scheme
+            ))),
+            scheme => Err(TelegrafError::BadProtocol(format!(
+                "unknown connection protocol {}",
+                scheme
+            ))),
+        }
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> TelegrafResult {
+        match self {
+            Self::Tcp(c) => c.write_all(buf).await?,
+            Self::Udp(c) => {
+                c.send(buf).await?;
+            }
+            #[cfg(target_family = "unix")]
+            Self::Unix(c) => c.write_all(buf).await?,
+            #[cfg(target_family = "unix")]
+            Self::Unixgram(c) => {
+                c.send(buf).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(c) => c.shutdown().await,
+            // Udp socket doesnt have a graceful close.
+            Self::Udp(_) => Ok(()),
+            #[cfg(target_family = "unix")]
+            Self::Unix(c) => c.shutdown().await,
+            #[cfg(target_family = "unix")]
+            Self::Unixgram(c) => c.shutdown(Shutdown::Both),
+        }
+    }
+}
+
+/// Address for tokio to resolve, without blocking on DNS.
+fn host_port(u: &Url) -> Result<String, TelegrafError> {
+    match (u.host_str(), u.port()) {
+        (Some(host), Some(port)) => Ok(format!("{}:{}", host, port)),
+        _ => Err(TelegrafError::BadProtocol(format!(
+            "missing host or port in {}",
+            u
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn can_write_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let mut c = AsyncClient::new(&url).await.unwrap();
+        c.write_points(&[point!("m", ("f", 1)), point!("n", ("g", true))])
+            .await
+            .unwrap();
+        c.close().await.unwrap();
+
+        let (mut sock, _) = listener.accept().await.unwrap();
+        let mut buf = String::new();
+        sock.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "m f=1i\nn g=true\n");
+    }
+
+    #[tokio::test]
+    async fn can_write_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("udp://{}", server.local_addr().unwrap());
+        let mut c = AsyncClient::new(&url)
+            .await
+            .unwrap()
+            .with_auto_timestamp(Precision::Seconds)
+            .with_clock(|| std::time::UNIX_EPOCH + std::time::Duration::from_secs(5));
+        c.write_point(&point!("m", ("f", 1))).await.unwrap();
+
+        let mut buf = [0; 64];
+        let n = server.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"m f=1i 5000000000\n");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn can_write_unix() {
+        let dir = std::env::temp_dir();
+        let stream_path = dir.join(format!("telegraf-async-{}.sock", std::process::id()));
+        let gram_path = dir.join(format!("telegraf-async-{}.gram", std::process::id()));
+        let _ = std::fs::remove_file(&stream_path);
+        let _ = std::fs::remove_file(&gram_path);
+
+        let listener = tokio::net::UnixListener::bind(&stream_path).unwrap();
+        let mut c = AsyncClient::new(&format!("unix://{}", stream_path.display()))
+            .await
+            .unwrap();
+        c.write_point(&point!("m", ("f", 1))).await.unwrap();
+        let (mut sock, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 7];
+        sock.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"m f=1i\n");
+
+        let server = UnixDatagram::bind(&gram_path).unwrap();
+        let mut c = AsyncClient::new(&format!("unixgram://{}", gram_path.display()))
+            .await
+            .unwrap();
+        c.write_point(&point!("m", ("f", 2))).await.unwrap();
+        let mut buf = [0; 64];
+        let n = server.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"m f=2i\n");
+
+        std::fs::remove_file(&stream_path).unwrap();
+        std::fs::remove_file(&gram_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_points() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("udp://{}", server.local_addr().unwrap());
+        let mut c = AsyncClient::new(&url).await.unwrap();
+        let res = c
+            .write_point(&Point::new("m".to_owned(), vec![], vec![], None))
+            .await;
+        assert!(matches!(res, Err(TelegrafError::InvalidPoint(_))));
+    }
+
+    #[test]
+    fn futures_are_send() {
+        fn assert_send<T: Send>(_: T) {}
+        #[allow(dead_code)]
+        fn check(c: &mut AsyncClient, pts: &[Point]) {
+            assert_send(c.write_points(pts));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_protocols() {
+        let res = AsyncClient::new("http://localhost:8186").await;
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+    }
+}
@@ -0,0 +1,525 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Client, Clock, FieldData, IntoTimestamp, Metric, Point, Precision, TelegrafError,
+    TelegrafResult,
+};
+
+/// Batching settings of a [BufferedClient].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use telegraf::BatchConfig;
+///
+/// let config = BatchConfig::new()
+///     .with_max_batch_points(500)
+///     .with_flush_interval(Duration::from_secs(5))
+///     .with_error_listener(|e| eprintln!("telegraf: {}", e));
+/// ```
+pub struct BatchConfig {
+    max_batch_points: usize,
+    max_batch_bytes: usize,
+    max_pending_points: usize,
+    flush_interval: Duration,
+    error_listener: Option<ErrorListener>,
+}
+
+type ErrorListener = Box<dyn FnMut(&TelegrafError) + Send>;
+
+/// Client that batches points in memory and writes them
+/// from a background thread.
+///
+/// Writing a point only validates it and adds it to the batch, so it
+/// can be done from hot paths, and from several threads through an
+/// [Arc]. The batch is written once it holds
+/// [BatchConfig::with_max_batch_points] points or about
+/// [BatchConfig::with_max_batch_bytes] bytes, or when the
+/// [BatchConfig::with_flush_interval] elapses, with one call to
+/// [Client::write_points] per batch of at most that size. Dropping
+/// the client writes the remaining points and waits for the
+/// background thread.
+///
+/// Errors of background writes are reported to the
+/// [BatchConfig::with_error_listener], and the points of the failed
+/// write are dropped. While Telegraf is slower than the points come
+/// in, writes fail once [BatchConfig::with_max_pending_points] are
+/// waiting. Combine with [Client::with_reconnect] to survive Telegraf
+/// restarts.
+///
+/// Automatic timestamps of the [Client] are taken when points are
+/// written to the buffer, not when the batch is flushed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use telegraf::{point, BatchConfig, BufferedClient, Client};
+///
+/// let client = Client::new("tcp://localhost:8094").unwrap();
+/// let buffered = BufferedClient::new(client, BatchConfig::new());
+/// buffered.write_point(&point!("requests", ("status", 200))).unwrap();
+/// ```
+pub struct BufferedClient {
+    shared: Arc<Shared>,
+    flusher: Option<thread::JoinHandle<()>>,
+}
+
+/// State shared with the background thread.
+struct Shared {
+    batch: Mutex<Batch>,
+    /// Signalled when the batch is full or the client is dropped.
+    ready: Condvar,
+    /// Held while writing, so that batches are written in order.
+    client: Mutex<Client>,
+    error_listener: Mutex<Option<ErrorListener>>,
+    max_batch_points: usize,
+    max_batch_bytes: usize,
+    max_pending_points: usize,
+    flush_interval: Duration,
+}
+
+struct Batch {
+    points: VecDeque<Point>,
+    /// Approximate size of `points` in line protocol.
+    bytes: usize,
+    auto_timestamp: Option<(Precision, Box<dyn Clock + Send>)>,
+    closed: bool,
+}
+
+impl BatchConfig {
+    /// Creates settings flushing batches of up to 1000 points or
+    /// 32 KiB, and at least every second, keeping up to 10000
+    /// points waiting.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of points a batch is flushed at.
+    pub fn with_max_batch_points(mut self, points: usize) -> Self {
+        self.max_batch_points = points.max(1);
+        self
+    }
+
+    /// Sets the approximate size in bytes a batch is flushed at. Keep
+    /// this below the datagram size limit for `udp://` connections.
+    pub fn with_max_batch_bytes(mut self, bytes: usize) -> Self {
+        self.max_batch_bytes = bytes.max(1);
+        self
+    }
+
+    /// Sets the number of points kept waiting to be written, beyond
+    /// which [BufferedClient::write_points] fails without adding any.
+    pub fn with_max_pending_points(mut self, points: usize) -> Self {
+        self.max_pending_points = points;
+        self
+    }
+
+    /// Sets the longest time points are kept in the batch.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Sets a function called with errors of batches written from
+    /// the background thread, e.g. to log them.
+    pub fn with_error_listener<F>(mut self, listener: F) -> Self
+    where
+        F: FnMut(&TelegrafError) + Send + 'static,
+    {
+        self.error_listener = Some(Box::new(listener));
+        self
+    }
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_points: 1000,
+            max_batch_bytes: 32 * 1024,
+            max_pending_points: 10_000,
+            flush_interval: Duration::from_secs(1),
+            error_listener: None,
+        }
+    }
+}
+
+impl BufferedClient {
+    /// Creates a buffered client writing with the given
+    /// client, and starts its background thread.
+    pub fn new(mut client: Client, config: BatchConfig) -> Self {
+        let auto_timestamp = client.pipeline.take_auto_timestamp();
+        let shared = Arc::new(Shared {
+            batch: Mutex::new(Batch {
+                points: VecDeque::new(),
+                bytes: 0,
+                auto_timestamp,
+                closed: false,
+            }),
+            ready: Condvar::new(),
+            client: Mutex::new(client),
+            error_listener: Mutex::new(config.error_listener),
+            max_batch_points: config.max_batch_points,
+            max_batch_bytes: config.max_batch_bytes,
+            max_pending_points: config.max_pending_points,
+            flush_interval: config.flush_interval,
+        });
+        let s = shared.clone();
+        let flusher = thread::Builder::new()
+            .name("telegraf-flush".to_owned())
+            .spawn(move || s.run())
+            .expect("failed to spawn flush thread");
+        Self {
+            shared,
+            flusher: Some(flusher),
+        }
+    }
+
+    /// Validates a point and adds it to the batch.
+    pub fn write_point(&self, pt: &Point) -> TelegrafResult {
+        self.write_points(std::slice::from_ref(pt))
+    }
+
+    /// Validates points and adds them to the batch. Invalid
+    /// points are reported before any is added.
+    ///
+    /// Fails with [TelegrafError::ConnectionError] if the points
+    /// do not fit in [BatchConfig::with_max_pending_points].
+    pub fn write_points(&self, pts: &[Point]) -> TelegrafResult {
+        for p in pts {
+            p.validate().map_err(TelegrafError::InvalidPoint)?;
+        }
+        self.shared.push(pts.iter().cloned())
+    }
+
+    /// Convenience wrapper around writing points for types
+    /// that implement [crate::Metric].
+    pub fn write<M: Metric>(&self, metric: &M) -> TelegrafResult {
+        let pt = metric.try_to_point()?;
+        pt.validate().map_err(TelegrafError::InvalidPoint)?;
+        self.shared.push(std::iter::once(pt))
+    }
+
+    /// Writes the batch now, returning the error of
+    /// the write instead of reporting it.
+    pub fn flush(&self) -> TelegrafResult {
+        self.shared.flush()
+    }
+}
+
+impl Drop for BufferedClient {
+    fn drop(&mut self) {
+        self.shared.lock_batch().closed = true;
+        self.shared.ready.notify_one();
+        if let Some(flusher) = self.flusher.take() {
+            let _ = flusher.join();
+        }
+    }
+}
+
+impl Shared {
+    fn lock_batch(&self) -> MutexGuard<'_, Batch> {
+        // Points are only added once valid, so the
+        // batch is usable even if a thread panicked.
+        self.batch.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, pts: impl ExactSizeIterator<Item = Point>) -> TelegrafResult {
+        let mut batch = self.lock_batch();
+        if batch.points.len() + pts.len() > self.max_pending_points {
+            return Err(TelegrafError::ConnectionError(format!(
+                "{} points are waiting to be written",
+                batch.points.len()
+            )));
+        }
+        let now = match &batch.auto_timestamp {
+            Some((p, clock)) => Some(clock.now().to_timestamp(*p)?),
+            None => None,
+        };
+        for mut p in pts {
+            p.timestamp = p.timestamp.or(now);
+            batch.bytes += approx_len(&p);
+            batch.points.push_back(p);
+        }
+        if batch.is_full(self) {
+            self.ready.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Writes the points waiting when called, in batches of at most
+    /// `max_batch_points` and about `max_batch_bytes`. Stops at the
+    /// first failed write, dropping its points.
+    fn flush(&self) -> TelegrafResult {
+        let mut client = self.client.lock().unwrap_or_else(|e| e.into_inner());
+        let mut left = self.lock_batch().points.len();
+        while left > 0 {
+            let pts = self.take_batch(left);
+            left -= pts.len();
+            client.write_points(&pts)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the next batch of at most `limit` points.
+    fn take_batch(&self, limit: usize) -> Vec<Point> {
+        let mut batch = self.lock_batch();
+        let (mut n, mut bytes) = (0, 0);
+        for p in batch.points.iter().take(limit.min(self.max_batch_points)) {
+            let len = approx_len(p);
+            if n > 0 && bytes + len > self.max_batch_bytes {
+                break;
+            }
+            n += 1;
+            bytes += len;
+        }
+        batch.bytes -= bytes;
+        batch.points.drain(..n).collect()
+    }
+
+    /// Body of the background thread.
+    fn run(&self) {
+        let mut deadline = Instant::now() + self.flush_interval;
+        loop {
+            let mut batch = self.lock_batch();
+            while !batch.closed && !batch.is_full(self) {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                batch = self
+                    .ready
+                    .wait_timeout(batch, deadline - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+            let closed = batch.closed;
+            drop(batch);
+
+            // Once closed, the points after a failed write are
+            // written too. Each failure drops points, so this ends.
+            while let Err(e) = self.flush() {
+                let mut listener = self
+                    .error_listener
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if let Some(listener) = &mut *listener {
+                    listener(&e);
+                }
+                if !closed {
+                    break;
+                }
+            }
+            if closed {
+                return;
+            }
+            deadline = Instant::now() + self.flush_interval;
+        }
+    }
+}
+
+impl Batch {
+    fn is_full(&self, shared: &Shared) -> bool {
+        self.points.len() >= shared.max_batch_points || self.bytes >= shared.max_batch_bytes
+    }
+}
+
+/// Size of a point in line protocol, ignoring escaping
+/// and assuming a nanosecond timestamp.
+fn approx_len(p: &Point) -> usize {
+    let tags: usize = p
+        .tags
+        .iter()
+        .map(|t| t.name.len() + t.value.len() + 2)
+        .sum();
+    let fields: usize = p
+        .fields
+        .iter()
+        .map(|f| {
+            f.name.len()
+                + 2
+                + match &f.value {
+                    FieldData::Boolean(_) => 5,
+                    FieldData::Str(s) => s.len() + 2,
+                    // Numbers are rarely written with all 20 digits.
+                    _ => 10,
+                }
+        })
+        .sum();
+    p.measurement.len() + tags + fields + 20
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+    use std::{
+        net::UdpSocket,
+        sync::atomic::{AtomicU64, Ordering},
+        time::UNIX_EPOCH,
+    };
+
+    /// Client writing to a UDP socket, returned with the
+    /// socket to receive from.
+    fn udp() -> (Client, UdpSocket) {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let url = format!("udp://{}", server.local_addr().unwrap());
+        (Client::new(&url).unwrap(), server)
+    }
+
+    fn recv(server: &UdpSocket) -> String {
+        let mut buf = [0; 1024];
+        let n = server.recv(&mut buf).unwrap();
+        String::from_utf8(buf[..n].to_vec()).unwrap()
+    }
+
+    fn hourly() -> BatchConfig {
+        BatchConfig::new().with_flush_interval(Duration::from_secs(3600))
+    }
+
+    #[test]
+    fn flushes_full_batches() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly().with_max_batch_points(3));
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.write_points(&[point!("m", ("f", 2)), point!("m", ("f", 3))])
+            .unwrap();
+        assert_eq!(recv(&server), "m f=1i\nm f=2i\nm f=3i\n");
+    }
+
+    #[test]
+    fn flushes_large_batches() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly().with_max_batch_bytes(80));
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        c.write_point(&point!("m", ("s", "x".repeat(10)))).unwrap();
+        assert_eq!(recv(&server), "m f=1i\nm f=2i\n");
+        assert_eq!(recv(&server), "m s=\"xxxxxxxxxx\"\n");
+    }
+
+    #[test]
+    fn waits_with_zero_byte_limit() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly().with_max_batch_bytes(0));
+        // An empty batch is not full, so the flush thread waits.
+        assert!(!c.shared.lock_batch().is_full(&c.shared));
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        assert_eq!(recv(&server), "m f=1i\n");
+    }
+
+    #[test]
+    fn splits_backlog_into_batches() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly().with_max_batch_points(2));
+        let pts: Vec<_> = (1..=5).map(|i| point!("m", ("f", i))).collect();
+        c.write_points(&pts).unwrap();
+        assert_eq!(recv(&server), "m f=1i\nm f=2i\n");
+        assert_eq!(recv(&server), "m f=3i\nm f=4i\n");
+        assert_eq!(recv(&server), "m f=5i\n");
+    }
+
+    #[test]
+    fn rejects_points_when_full() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly().with_max_pending_points(2));
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        let res = c.write_points(&[point!("m", ("f", 2)), point!("m", ("f", 3))]);
+        assert!(matches!(res, Err(TelegrafError::ConnectionError(_))));
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        c.flush().unwrap();
+        assert_eq!(recv(&server), "m f=1i\nm f=2i\n");
+    }
+
+    #[test]
+    fn flushes_on_interval() {
+        let (client, server) = udp();
+        let config = BatchConfig::new().with_flush_interval(Duration::from_millis(20));
+        let c = BufferedClient::new(client, config);
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        assert_eq!(recv(&server), "m f=1i\n");
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        assert_eq!(recv(&server), "m f=2i\n");
+    }
+
+    #[test]
+    fn can_flush() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly());
+        c.flush().unwrap();
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.flush().unwrap();
+        assert_eq!(recv(&server), "m f=1i\n");
+    }
+
+    #[test]
+    fn flushes_on_drop() {
+        let (client, server) = udp();
+        let c = BufferedClient::new(client, hourly());
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        drop(c);
+        assert_eq!(recv(&server), "m f=1i\nm f=2i\n");
+    }
+
+    #[test]
+    fn can_share_between_threads() {
+        let (client, server) = udp();
+        let c = Arc::new(BufferedClient::new(client, hourly()));
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let c = c.clone();
+                thread::spawn(move || c.write_point(&point!("m", ("f", i))).unwrap())
+            })
+            .collect();
+        for w in writers {
+            w.join().unwrap();
+        }
+        c.flush().unwrap();
+        assert_eq!(recv(&server).lines().count(), 4);
+    }
+
+    #[test]
+    fn stamps_points_when_written() {
+        let (client, server) = udp();
+        let secs = AtomicU64::new(0);
+        let client = client
+            .with_auto_timestamp(Precision::Seconds)
+            .with_clock(move || {
+                UNIX_EPOCH + Duration::from_secs(secs.fetch_add(1, Ordering::SeqCst) + 1)
+            });
+        let c = BufferedClient::new(client, hourly());
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        c.flush().unwrap();
+        assert_eq!(recv(&server), "m f=1i 1000000000\nm f=2i 2000000000\n");
+    }
+
+    #[test]
+    fn reports_errors() {
+        let (client, server) = udp();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let e = errors.clone();
+        let config =
+            hourly().with_error_listener(move |err| e.lock().unwrap().push(err.to_string()));
+        let c = BufferedClient::new(client, config);
+
+        let res = c.write_point(&Point::new("m".to_owned(), vec![], vec![], None));
+        assert!(matches!(res, Err(TelegrafError::InvalidPoint(_))));
+        // Too large for a datagram, so only the point after it arrives.
+        let s = "x".repeat(40_000);
+        let big = point!("m", ("a", s.clone())("b", s));
+        c.write_points(&[big, point!("m", ("f", 1))]).unwrap();
+        drop(c);
+        assert_eq!(errors.lock().unwrap().len(), 1);
+        assert_eq!(recv(&server), "m f=1i\n");
+    }
+}
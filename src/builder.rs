@@ -0,0 +1,143 @@
+use crate::{
+    protocol::{Field, Tag},
+    IntoFieldData, IntoTimestamp, Point, Precision, TelegrafError, Timestamp,
+};
+
+/// Builder for assembling a [crate::Point] piece by piece,
+/// e.g. when tags or fields are only known at runtime.
+///
+/// Created via [crate::Point::builder].
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::Point;
+///
+/// let p = Point::builder("measurement")
+///     .tag("tag1", "tag1value")
+///     .field("field1", 10)
+///     .field("field2", "anything!")
+///     .timestamp(100u64)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct PointBuilder {
+    measurement: String,
+    tags: Vec<Tag>,
+    fields: Vec<Field>,
+    timestamp: Option<Result<Timestamp, TelegrafError>>,
+}
+
+impl Point {
+    /// Starts building a point for the given measurement.
+    pub fn builder(measurement: impl Into<String>) -> PointBuilder {
+        PointBuilder {
+            measurement: measurement.into(),
+            tags: Vec::new(),
+            fields: Vec::new(),
+            timestamp: None,
+        }
+    }
+}
+
+impl PointBuilder {
+    /// Adds a tag.
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Adds a field.
+    pub fn field(mut self, name: impl Into<String>, value: impl IntoFieldData) -> Self {
+        self.fields.push(Field {
+            name: name.into(),
+            value: value.field_data(),
+        });
+        self
+    }
+
+    /// Sets the timestamp. Plain integers are in nanoseconds.
+    ///
+    /// Times that cannot be converted are reported by
+    /// [PointBuilder::build].
+    pub fn timestamp(mut self, ts: impl IntoTimestamp) -> Self {
+        self.timestamp = Some(ts.to_timestamp(Precision::Nanoseconds));
+        self
+    }
+
+    /// Builds the point, checking it with [crate::Point::validate].
+    pub fn build(self) -> Result<Point, TelegrafError> {
+        let point = Point {
+            measurement: self.measurement,
+            tags: self.tags,
+            fields: self.fields,
+            timestamp: self.timestamp.transpose()?,
+        };
+        point.validate().map_err(TelegrafError::InvalidPoint)?;
+        Ok(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::{point, ValidationError};
+
+    #[test]
+    fn can_build_point() {
+        let p = Point::builder("m")
+            .tag("t", "v")
+            .tag(String::from("t2"), String::from("v2"))
+            .field("f", 1)
+            .field("f2", "s")
+            .timestamp(10u64)
+            .build()
+            .unwrap();
+        let exp = point!("m", ("t", "v")("t2", "v2"), ("f", 1)("f2", "s"); 10);
+        assert_eq!(p, exp);
+    }
+
+    #[test]
+    fn can_build_point_conditionally() {
+        let mut b = Point::builder("m").field("f", 1.5);
+        for i in 0..2 {
+            b = b.tag(format!("t{}", i), i.to_string());
+        }
+        let p = b.build().unwrap();
+        assert_eq!(p, point!("m", ("t0", "0")("t1", "1"), ("f", 1.5)));
+    }
+
+    #[test]
+    fn can_build_point_with_system_time() {
+        let p = Point::builder("m")
+            .field("f", 1)
+            .timestamp(UNIX_EPOCH + Duration::from_nanos(5))
+            .build()
+            .unwrap();
+        assert_eq!(p.timestamp, Some(Timestamp::from(5)));
+    }
+
+    #[test]
+    fn cannot_build_invalid_point() {
+        match Point::builder("m").tag("t", "").build() {
+            Err(TelegrafError::InvalidPoint(errs)) => assert_eq!(
+                errs,
+                vec![
+                    ValidationError::NoFields,
+                    ValidationError::EmptyTagValue("t".to_owned())
+                ]
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let ts = UNIX_EPOCH - Duration::from_secs(1);
+        let res = Point::builder("m").field("f", 1).timestamp(ts).build();
+        assert!(matches!(res, Err(TelegrafError::BadTimestamp(_))));
+    }
+}
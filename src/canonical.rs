@@ -0,0 +1,233 @@
+//! Order-independent equality and hashing of points.
+//!
+//! Two points are equal when they have the same measurement,
+//! timestamp, tags and fields, regardless of the order tags and
+//! fields were added in. Timestamps are compared as nanoseconds,
+//! so the same instant in different precisions is equal. Floats are compared by their bits, so a
+//! point always equals itself and equal points hash equally.
+
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    protocol::{tag_order, AsField, AsTag, FieldDataRef},
+    Point, PointRef, Timestamp,
+};
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.measurement == other.measurement
+            && nanos(self.timestamp) == nanos(other.timestamp)
+            && tags_eq(&self.tags, &other.tags)
+            && fields_eq(&self.fields, &other.fields)
+    }
+}
+
+impl Eq for Point {}
+
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.measurement.hash(state);
+        nanos(self.timestamp).hash(state);
+        hash_tags(&self.tags, state);
+        hash_fields(&self.fields, state);
+    }
+}
+
+impl PartialEq for PointRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.measurement == other.measurement
+            && nanos(self.timestamp) == nanos(other.timestamp)
+            && tags_eq(&self.tags, &other.tags)
+            && fields_eq(&self.fields, &other.fields)
+    }
+}
+
+impl Eq for PointRef<'_> {}
+
+impl Hash for PointRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.measurement.hash(state);
+        nanos(self.timestamp).hash(state);
+        hash_tags(&self.tags, state);
+        hash_fields(&self.fields, state);
+    }
+}
+
+/// Nanoseconds since the epoch, which cannot overflow a `u128`.
+fn nanos(t: Option<Timestamp>) -> Option<u128> {
+    t.map(|t| t.value as u128 * t.precision.nanos() as u128)
+}
+
+fn tags_eq<T: AsTag>(a: &[T], b: &[T]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let same = |x: &T, y: &T| tag_order(x, y) == Ordering::Equal;
+    if a.iter().zip(b).all(|(x, y)| same(x, y)) {
+        return true;
+    }
+    sorted_tags(a)
+        .into_iter()
+        .zip(sorted_tags(b))
+        .all(|(x, y)| same(x, y))
+}
+
+fn fields_eq<F: AsField>(a: &[F], b: &[F]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let same = |x: &F, y: &F| field_order(x, y) == Ordering::Equal;
+    if a.iter().zip(b).all(|(x, y)| same(x, y)) {
+        return true;
+    }
+    sorted_fields(a)
+        .into_iter()
+        .zip(sorted_fields(b))
+        .all(|(x, y)| same(x, y))
+}
+
+fn hash_tags<T: AsTag, H: Hasher>(tags: &[T], state: &mut H) {
+    state.write_usize(tags.len());
+    for t in sorted_tags(tags) {
+        t.name().hash(state);
+        t.value().hash(state);
+    }
+}
+
+fn hash_fields<F: AsField, H: Hasher>(fields: &[F], state: &mut H) {
+    state.write_usize(fields.len());
+    for f in sorted_fields(fields) {
+        f.name().hash(state);
+        match f.value() {
+            FieldDataRef::Boolean(b) => (0u8, b).hash(state),
+            FieldDataRef::UNumber(n) => (1u8, n).hash(state),
+            FieldDataRef::Number(n) => (2u8, n).hash(state),
+            FieldDataRef::Float(v) => (3u8, v.to_bits()).hash(state),
+            FieldDataRef::Str(s) => (4u8, s).hash(state),
+        }
+    }
+}
+
+fn sorted_tags<T: AsTag>(tags: &[T]) -> Vec<&T> {
+    let mut v: Vec<&T> = tags.iter().collect();
+    v.sort_by(|a, b| tag_order(*a, *b));
+    v
+}
+
+fn sorted_fields<F: AsField>(fields: &[F]) -> Vec<&F> {
+    let mut v: Vec<&F> = fields.iter().collect();
+    v.sort_by(|a, b| field_order(*a, *b));
+    v
+}
+
+/// Orders fields by key, then by value.
+fn field_order<F: AsField>(a: &F, b: &F) -> Ordering {
+    a.name()
+        .cmp(b.name())
+        .then_with(|| value_order(&a.value(), &b.value()))
+}
+
+/// Total order over field values. Values of different
+/// types are ordered by type.
+fn value_order(a: &FieldDataRef<'_>, b: &FieldDataRef<'_>) -> Ordering {
+    use FieldDataRef::*;
+
+    fn rank(v: &FieldDataRef<'_>) -> u8 {
+        match v {
+            Boolean(_) => 0,
+            UNumber(_) => 1,
+            Number(_) => 2,
+            Float(_) => 3,
+            Str(_) => 4,
+        }
+    }
+
+    match (a, b) {
+        (Boolean(a), Boolean(b)) => a.cmp(b),
+        (UNumber(a), UNumber(b)) => a.cmp(b),
+        (Number(a), Number(b)) => a.cmp(b),
+        (Float(a), Float(b)) => a.total_cmp(b),
+        (Str(a), Str(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{hash_map::DefaultHasher, HashSet};
+
+    use super::*;
+    use crate::{point, point_ref, Precision};
+
+    fn hash_of<T: Hash>(t: &T) -> u64 {
+        let mut h = DefaultHasher::new();
+        t.hash(&mut h);
+        h.finish()
+    }
+
+    #[test]
+    fn ignores_order() {
+        let a = point!("m", ("t1", "a")("t2", "b"), ("f1", 1)("f2", 2.5); 10);
+        let b = point!("m", ("t2", "b")("t1", "a"), ("f2", 2.5)("f1", 1); 10);
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let a = PointRef::from(&a);
+        let b = PointRef::from(&b);
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn compares_contents() {
+        let p = point!("m", ("t", "a"), ("f", 1));
+        assert_ne!(p, point!("m", ("t", "b"), ("f", 1)));
+        assert_ne!(p, point!("m", ("t", "a"), ("f", 1u64)));
+        assert_ne!(p, point!("m", ("t", "a"), ("f", 1)("g", 1)));
+        assert_ne!(p, point!("m", ("t", "a"), ("f", 1); 1));
+        assert_ne!(p, point!("n", ("t", "a"), ("f", 1)));
+        assert_ne!(
+            point_ref!("m", ("f", 1)("f", 2)("g", 1)),
+            point_ref!("m", ("f", 1)("g", 2)("g", 1))
+        );
+    }
+
+    #[test]
+    fn compares_timestamps_across_precisions() {
+        let a = point!("m", ("f", 1); Timestamp::new(1, Precision::Seconds));
+        let b = point!("m", ("f", 1); Timestamp::new(1_000, Precision::Milliseconds));
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_eq!(PointRef::from(&a), PointRef::from(&b));
+        assert_ne!(
+            a,
+            point!("m", ("f", 1); Timestamp::new(1, Precision::Milliseconds))
+        );
+
+        let max = point!("m", ("f", 1); Timestamp::new(u64::MAX, Precision::Seconds));
+        assert_eq!(max, max.clone());
+        assert_ne!(max, point!("m", ("f", 1); u64::MAX));
+    }
+
+    #[test]
+    fn compares_floats_by_bits() {
+        let nan = point!("m", ("f", f64::NAN));
+        assert_eq!(nan, nan.clone());
+        assert_ne!(point!("m", ("f", 0.0)), point!("m", ("f", -0.0)));
+    }
+
+    #[test]
+    fn can_dedup_points() {
+        let set: HashSet<Point> = vec![
+            point!("m", ("a", "1")("b", "2"), ("f", 1)),
+            point!("m", ("b", "2")("a", "1"), ("f", 1)),
+            point!("m", ("a", "1")("b", "3"), ("f", 1)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+    }
+}
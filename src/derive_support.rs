@@ -0,0 +1,30 @@
+//! Support code for `#[derive(Metric)]`. Not part of the public API.
+
+use crate::{IntoTimestamp, Precision, TelegrafError, Timestamp};
+
+/// Wraps the timestamp member of a derived metric. Called as
+/// `(&&TimestampMember(v)).member_timestamp(p)`, method resolution
+/// picks [IntoTimestamp] when the member implements it and falls
+/// back to `Into<u64>`, which timestamps were limited to before,
+/// so `u32` and other narrower integers keep working.
+pub struct TimestampMember<'a, T>(pub &'a T);
+
+pub trait ViaIntoTimestamp {
+    fn member_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError>;
+}
+
+impl<T: IntoTimestamp> ViaIntoTimestamp for &TimestampMember<'_, T> {
+    fn member_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        IntoTimestamp::to_timestamp(self.0, precision)
+    }
+}
+
+pub trait ViaIntoU64 {
+    fn member_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError>;
+}
+
+impl<T: Clone + Into<u64>> ViaIntoU64 for TimestampMember<'_, T> {
+    fn member_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        Ok(Timestamp::new(self.0.clone().into(), precision))
+    }
+}
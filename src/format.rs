@@ -0,0 +1,180 @@
+//! Output formats other than line protocol.
+//!
+//! The format a [crate::Client] writes is chosen with
+//! [crate::Client::with_format]. Every format is built from the
+//! same [crate::Point] model, so derived [crate::Metric]s can be
+//! sent in any of them. Formats this crate does not provide can
+//! be added by implementing [Serializer].
+
+use std::{fmt, io};
+
+use crate::{LineEncoder, PointRef, TelegrafError};
+
+mod csv;
+mod graphite;
+mod json;
+mod msgpack;
+mod opentsdb;
+mod prometheus;
+mod statsd;
+
+pub use csv::AnnotatedCsv;
+pub use graphite::{Graphite, DEFAULT_GRAPHITE_TEMPLATE};
+pub use json::Json;
+pub use msgpack::MsgPack;
+pub use opentsdb::OpenTsdb;
+pub use prometheus::{Prometheus, PrometheusType};
+pub use statsd::{Statsd, StatsdKind, StatsdTagStyle};
+
+/// Format of the data written by a [crate::Client].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Format {
+    /// InfluxDB line protocol, for `data_format = "influx"`.
+    #[default]
+    LineProtocol,
+    /// Graphite plaintext protocol, for `data_format = "graphite"`.
+    Graphite(Graphite),
+    /// Telegraf JSON, for `data_format = "json"`.
+    Json(Json),
+    /// Telegraf MessagePack, for `data_format = "msgpack"`.
+    MsgPack(MsgPack),
+    /// OpenTSDB telnet `put` lines, for `data_format = "opentsdb"`.
+    OpenTsdb(OpenTsdb),
+    /// StatsD, for Telegraf's `statsd` input. Used by default
+    /// for `statsd://` connections.
+    Statsd(Statsd),
+}
+
+/// Encodes points into the bytes a [crate::Client] writes.
+///
+/// Implemented by [LineEncoder], every format in this module and
+/// [Format] itself. Custom implementations are selected with
+/// [crate::Client::with_serializer]. The client stamps points with
+/// automatic timestamps before they are serialized.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::Serializer, point, PointRef, TelegrafError};
+///
+/// /// Writes the measurement and name of every field.
+/// struct Plain;
+///
+/// impl Serializer for Plain {
+///     fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+///         for f in pt.fields.iter() {
+///             buf.extend_from_slice(format!("{} {}\n", pt.measurement, f.name).as_bytes());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let p = point!("cpu", ("host", "a"), ("load", 0.5));
+/// let mut buf = Vec::new();
+/// Plain.serialize(&mut buf, &PointRef::from(&p)).unwrap();
+/// assert_eq!(buf, b"cpu load\n");
+/// ```
+pub trait Serializer {
+    /// Appends a point to `buf`.
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError>;
+
+    /// Appends the points of a single [crate::Client::write_points]
+    /// batch to `buf`.
+    ///
+    /// Defaults to serializing each point in turn.
+    fn serialize_batch(
+        &mut self,
+        buf: &mut Vec<u8>,
+        pts: &[PointRef],
+    ) -> Result<(), TelegrafError> {
+        pts.iter().try_for_each(|p| self.serialize(buf, p))
+    }
+}
+
+impl Serializer for Format {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        match self {
+            Format::LineProtocol => LineEncoder::new().serialize(buf, pt),
+            Format::Graphite(g) => g.serialize(buf, pt),
+            Format::Json(j) => j.serialize(buf, pt),
+            Format::MsgPack(m) => m.serialize(buf, pt),
+            Format::OpenTsdb(o) => o.serialize(buf, pt),
+            Format::Statsd(s) => s.serialize(buf, pt),
+        }
+    }
+
+    fn serialize_batch(
+        &mut self,
+        buf: &mut Vec<u8>,
+        pts: &[PointRef],
+    ) -> Result<(), TelegrafError> {
+        match self {
+            Format::Json(j) => j.serialize_batch(buf, pts),
+            _ => pts.iter().try_for_each(|p| self.serialize(buf, p)),
+        }
+    }
+}
+
+/// Runs `write` against `w`, reporting a failure of the writer as
+/// [TelegrafError::IoError]. Shared by every text format, so errors
+/// are mapped the same way and [IoWriter] can restore the I/O error
+/// behind them.
+pub(crate) fn write_with<W: fmt::Write>(
+    w: &mut W,
+    write: impl FnOnce(&mut W) -> fmt::Result,
+) -> Result<(), TelegrafError> {
+    write(w).map_err(|e| TelegrafError::IoError(io::Error::other(e)))
+}
+
+/// Writes `s`, replacing characters for which `keep`
+/// returns false with `_`.
+pub(crate) fn write_sanitized<W: fmt::Write>(
+    w: &mut W,
+    s: &str,
+    keep: impl Fn(char) -> bool,
+) -> fmt::Result {
+    for c in s.chars() {
+        w.write_char(if keep(c) { c } else { '_' })?;
+    }
+    Ok(())
+}
+
+/// Adapts an [io::Write] for use as a [fmt::Write], keeping
+/// the underlying error.
+pub(crate) struct IoWriter<'a, W> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write> IoWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// Replaces the generic write error with the I/O error behind it.
+    pub(crate) fn finish(self, res: Result<(), TelegrafError>) -> Result<(), TelegrafError> {
+        match self.error {
+            Some(e) => Err(TelegrafError::IoError(e)),
+            None => res,
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Adapts a byte buffer for use as a [fmt::Write].
+pub(crate) struct Bytes<'a>(pub(crate) &'a mut Vec<u8>);
+
+impl fmt::Write for Bytes<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
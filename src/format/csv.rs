@@ -0,0 +1,296 @@
+use std::{collections::HashMap, fmt};
+
+use super::write_with;
+use crate::{
+    protocol::tag_order, FieldData, Point, Precision, SeriesKey, Tag, TelegrafError, Timestamp,
+};
+
+/// Encoder for InfluxDB annotated CSV, e.g. to export points to
+/// InfluxDB tooling or spreadsheets.
+///
+/// Every field of a point becomes a row with `_time`, `_value`,
+/// `_field` and `_measurement` columns, followed by a column per tag.
+/// Rows sharing a measurement, tag set, field and value type form a
+/// table. Consecutive tables with the same columns and `_value` type
+/// share `#datatype`, `#group` and `#default` annotations; a new block
+/// is started, after a blank line, whenever they change.
+///
+/// Timestamps are written as RFC 3339 in UTC. Points without
+/// one leave `_time` empty.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::AnnotatedCsv, point};
+///
+/// let pts = [point!("cpu", ("host", "a"), ("load", 0.5); 1_000_000_000)];
+/// let mut buf = String::new();
+/// AnnotatedCsv::new().encode_points(&mut buf, &pts).unwrap();
+/// assert_eq!(
+///     buf,
+///     "#datatype,string,long,dateTime:RFC3339,double,string,string,string\n\
+///      #group,false,false,false,false,true,true,true\n\
+///      #default,_result,,,,,,\n\
+///      ,result,table,_time,_value,_field,_measurement,host\n\
+///      ,,0,1970-01-01T00:00:01Z,0.5,load,cpu,a\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotatedCsv {}
+
+/// Rows of one field of one series.
+struct Table<'a> {
+    measurement: &'a str,
+    field: &'a str,
+    tags: Vec<&'a Tag>,
+    datatype: &'static str,
+    rows: Vec<(Option<Timestamp>, &'a FieldData)>,
+}
+
+impl Table<'_> {
+    /// Whether both tables can share annotations.
+    fn same_schema(&self, other: &Table) -> bool {
+        self.datatype == other.datatype
+            && self.tags.len() == other.tags.len()
+            && self
+                .tags
+                .iter()
+                .zip(&other.tags)
+                .all(|(a, b)| a.name == b.name)
+    }
+}
+
+impl AnnotatedCsv {
+    /// Creates an encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the tables of several points.
+    pub fn encode_points<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pts: &[Point],
+    ) -> Result<(), TelegrafError> {
+        // Tables are numbered in order of first appearance.
+        let mut index: HashMap<(SeriesKey, &str, &str), usize> = HashMap::new();
+        let mut tables: Vec<Table> = Vec::new();
+        for pt in pts {
+            if let Some(t) = pt.timestamp {
+                if t.to_precision(Precision::Nanoseconds).is_none() {
+                    return Err(TelegrafError::BadProtocol(format!(
+                        "timestamp {} is out of range",
+                        t.value
+                    )));
+                }
+            }
+            let key = pt.series_key();
+            for f in &pt.fields {
+                let datatype = datatype(&f.value);
+                let i = *index
+                    .entry((key.clone(), f.name.as_str(), datatype))
+                    .or_insert_with(|| {
+                        let mut tags: Vec<&Tag> = pt.tags.iter().collect();
+                        tags.sort_by(|a, b| tag_order(*a, *b));
+                        tables.push(Table {
+                            measurement: &pt.measurement,
+                            field: &f.name,
+                            tags,
+                            datatype,
+                            rows: Vec::new(),
+                        });
+                        tables.len() - 1
+                    });
+                tables[i].rows.push((pt.timestamp, &f.value));
+            }
+        }
+
+        write_with(w, |w| {
+            for (i, table) in tables.iter().enumerate() {
+                if i == 0 || !table.same_schema(&tables[i - 1]) {
+                    if i > 0 {
+                        w.write_char('\n')?;
+                    }
+                    write_annotations(w, table)?;
+                }
+                for (ts, value) in &table.rows {
+                    write!(w, ",,{},", i)?;
+                    if let Some(ts) = ts {
+                        write_time(w, *ts)?;
+                    }
+                    w.write_char(',')?;
+                    match value {
+                        FieldData::Boolean(b) => write!(w, "{}", b)?,
+                        FieldData::UNumber(n) => write!(w, "{}", n)?,
+                        FieldData::Number(n) => write!(w, "{}", n)?,
+                        FieldData::Float(v) if v.is_nan() => w.write_str("NaN")?,
+                        FieldData::Float(v) if v.is_infinite() => {
+                            w.write_str(if *v > 0.0 { "+Inf" } else { "-Inf" })?
+                        }
+                        FieldData::Float(v) => write!(w, "{}", v)?,
+                        FieldData::Str(s) => write_cell(w, s)?,
+                    }
+                    w.write_char(',')?;
+                    write_cell(w, table.field)?;
+                    w.write_char(',')?;
+                    write_cell(w, table.measurement)?;
+                    for t in &table.tags {
+                        w.write_char(',')?;
+                        write_cell(w, &t.value)?;
+                    }
+                    w.write_char('\n')?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+fn datatype(value: &FieldData) -> &'static str {
+    match value {
+        FieldData::Boolean(_) => "boolean",
+        FieldData::UNumber(_) => "unsignedLong",
+        FieldData::Number(_) => "long",
+        FieldData::Float(_) => "double",
+        FieldData::Str(_) => "string",
+    }
+}
+
+fn write_annotations<W: fmt::Write>(w: &mut W, table: &Table) -> fmt::Result {
+    write!(
+        w,
+        "#datatype,string,long,dateTime:RFC3339,{},string,string",
+        table.datatype
+    )?;
+    for _ in &table.tags {
+        w.write_str(",string")?;
+    }
+    w.write_str("\n#group,false,false,false,false,true,true")?;
+    for _ in &table.tags {
+        w.write_str(",true")?;
+    }
+    w.write_str("\n#default,_result,,,,,")?;
+    for _ in &table.tags {
+        w.write_char(',')?;
+    }
+    w.write_str("\n,result,table,_time,_value,_field,_measurement")?;
+    for t in &table.tags {
+        w.write_char(',')?;
+        write_cell(w, &t.name)?;
+    }
+    w.write_char('\n')
+}
+
+/// Writes a timestamp as RFC 3339 in UTC, with as many
+/// fractional digits as needed.
+fn write_time<W: fmt::Write>(w: &mut W, ts: Timestamp) -> fmt::Result {
+    // Checked to fit before anything is written.
+    let nanos = ts.to_precision(Precision::Nanoseconds).unwrap_or_default();
+    let secs = nanos / 1_000_000_000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    write!(
+        w,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )?;
+    let frac = nanos % 1_000_000_000;
+    if frac != 0 {
+        let digits = format!("{:09}", frac);
+        write!(w, ".{}", digits.trim_end_matches('0'))?;
+    }
+    w.write_char('Z')
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian
+/// date, following Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Writes a CSV cell, quoted if needed.
+fn write_cell<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    if !s.contains([',', '"', '\n', '\r']) {
+        return w.write_str(s);
+    }
+    w.write_char('"')?;
+    for c in s.chars() {
+        if c == '"' {
+            w.write_char('"')?;
+        }
+        w.write_char(c)?;
+    }
+    w.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+
+    #[test]
+    fn can_encode_points() {
+        let pts = [
+            point!("m", ("b", "x,y")("a", "1"), ("f", 1.5)("s", "q\"r"); 1_700_000_000_123_000_000),
+            point!("m", ("a", "1")("b", "x,y"), ("f", 2.0); 1_700_000_001_000_000_000),
+            point!("m", ("a", "2")("b", "z"), ("f", 3.0)),
+            point!("n", ("u", 1u64)("ok", true)),
+        ];
+        let mut buf = String::new();
+        AnnotatedCsv::new().encode_points(&mut buf, &pts).unwrap();
+        assert_eq!(
+            buf,
+            "#datatype,string,long,dateTime:RFC3339,double,string,string,string,string\n\
+             #group,false,false,false,false,true,true,true,true\n\
+             #default,_result,,,,,,,\n\
+             ,result,table,_time,_value,_field,_measurement,a,b\n\
+             ,,0,2023-11-14T22:13:20.123Z,1.5,f,m,1,\"x,y\"\n\
+             ,,0,2023-11-14T22:13:21Z,2,f,m,1,\"x,y\"\n\
+             \n\
+             #datatype,string,long,dateTime:RFC3339,string,string,string,string,string\n\
+             #group,false,false,false,false,true,true,true,true\n\
+             #default,_result,,,,,,,\n\
+             ,result,table,_time,_value,_field,_measurement,a,b\n\
+             ,,1,2023-11-14T22:13:20.123Z,\"q\"\"r\",s,m,1,\"x,y\"\n\
+             \n\
+             #datatype,string,long,dateTime:RFC3339,double,string,string,string,string\n\
+             #group,false,false,false,false,true,true,true,true\n\
+             #default,_result,,,,,,,\n\
+             ,result,table,_time,_value,_field,_measurement,a,b\n\
+             ,,2,,3,f,m,2,z\n\
+             \n\
+             #datatype,string,long,dateTime:RFC3339,unsignedLong,string,string\n\
+             #group,false,false,false,false,true,true\n\
+             #default,_result,,,,,\n\
+             ,result,table,_time,_value,_field,_measurement\n\
+             ,,3,,1,u,n\n\
+             \n\
+             #datatype,string,long,dateTime:RFC3339,boolean,string,string\n\
+             #group,false,false,false,false,true,true\n\
+             #default,_result,,,,,\n\
+             ,result,table,_time,_value,_field,_measurement\n\
+             ,,4,,true,ok,n\n"
+        );
+    }
+
+    #[test]
+    fn can_convert_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+
+use super::{write_sanitized, write_with, Bytes, Serializer};
+use crate::{
+    protocol::{AsField, AsTag, FieldDataRef},
+    Point, PointRef, Precision, TelegrafError, Timestamp,
+};
+
+/// Template used by Telegraf's Graphite serializer.
+pub const DEFAULT_GRAPHITE_TEMPLATE: &str = "host.tags.measurement.field";
+
+/// Serializer for the Graphite plaintext protocol, as read by
+/// Telegraf inputs with `data_format = "graphite"`.
+///
+/// Every numeric field of a point is written as its own line:
+///
+/// `<path> <value> [<timestamp>]`
+///
+/// The path is built from a `.` separated template, where
+/// `measurement` and `field` are replaced by the measurement and
+/// field names, `tags` by the values of all tags not named
+/// elsewhere in the template (sorted by key) and any other word
+/// by the value of the tag with that key. Parts that are missing
+/// are skipped, as is the `field` part of fields named `value`.
+///
+/// Characters other than letters, digits and `-:._=` are replaced
+/// with `_`. Booleans are written as `1` or `0`, string fields are
+/// skipped and timestamps are written in seconds.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::Graphite, point};
+///
+/// let p = point!("cpu", ("host", "web01")("dc", "eu 1"), ("load", 0.5); 1_000_000_000);
+/// let mut buf = String::new();
+/// Graphite::new().encode_point(&mut buf, &p).unwrap();
+/// assert_eq!(buf, "web01.eu_1.cpu.load 0.5 1\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graphite {
+    template: Vec<String>,
+    separator: String,
+}
+
+impl Default for Graphite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Graphite {
+    /// Creates a serializer using [DEFAULT_GRAPHITE_TEMPLATE]
+    /// and `.` as separator.
+    pub fn new() -> Self {
+        Self {
+            template: parse_template(DEFAULT_GRAPHITE_TEMPLATE),
+            separator: String::from("."),
+        }
+    }
+
+    /// Sets the `.` separated template metric paths are built from.
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = parse_template(template);
+        self
+    }
+
+    /// Sets the separator placed between the parts of a path.
+    ///
+    /// Defaults to `.`.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Appends the Graphite lines of a point.
+    pub fn encode_point<W: fmt::Write>(&self, w: &mut W, pt: &Point) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    /// Appends the Graphite lines of a borrowed point.
+    pub fn encode_point_ref<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    fn write_point<W, T, F>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TelegrafError>
+    where
+        W: fmt::Write,
+        T: AsTag,
+        F: AsField,
+    {
+        let ts = match timestamp {
+            Some(t) => Some(t.to_precision(Precision::Seconds).ok_or_else(|| {
+                TelegrafError::BadProtocol(format!("timestamp {} is out of range", t.value))
+            })?),
+            None => None,
+        };
+
+        write_with(w, |w| {
+            for f in fields {
+                let value = f.value();
+                if let FieldDataRef::Str(_) = value {
+                    continue;
+                }
+                self.write_path(w, measurement, tags, f.name())?;
+                match value {
+                    FieldDataRef::Boolean(b) => write!(w, " {}", b as u8)?,
+                    FieldDataRef::UNumber(n) => write!(w, " {}", n)?,
+                    FieldDataRef::Number(n) => write!(w, " {}", n)?,
+                    FieldDataRef::Float(v) => write!(w, " {}", v)?,
+                    FieldDataRef::Str(_) => unreachable!(),
+                }
+                if let Some(ts) = ts {
+                    write!(w, " {}", ts)?;
+                }
+                w.write_char('\n')?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_path<W: fmt::Write, T: AsTag>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        field: &str,
+    ) -> fmt::Result {
+        let named = |name: &str| self.template.iter().any(|p| p == name);
+        let field = if field == "value" { "" } else { field };
+
+        let mut first = true;
+        let mut part = |w: &mut W, s: &str| -> fmt::Result {
+            if s.is_empty() {
+                return Ok(());
+            }
+            if !first {
+                w.write_str(&self.separator)?;
+            }
+            first = false;
+            write_sanitized(w, s, |c| {
+                c.is_alphanumeric() || matches!(c, '-' | ':' | '.' | '_' | '=')
+            })
+        };
+
+        let mut has_field = false;
+        for p in &self.template {
+            match p.as_str() {
+                "measurement" => part(w, measurement)?,
+                "field" => {
+                    has_field = true;
+                    part(w, field)?;
+                }
+                "tags" => {
+                    let mut rest: Vec<&T> = tags.iter().filter(|t| !named(t.name())).collect();
+                    rest.sort_by(|a, b| a.name().cmp(b.name()));
+                    for t in rest {
+                        part(w, t.value())?;
+                    }
+                }
+                name => {
+                    if let Some(t) = tags.iter().find(|t| t.name() == name) {
+                        part(w, t.value())?;
+                    }
+                }
+            }
+        }
+        if !has_field {
+            part(w, field)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serializer for Graphite {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(&mut Bytes(buf), pt)
+    }
+}
+
+fn parse_template(template: &str) -> Vec<String> {
+    template
+        .split('.')
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref};
+
+    fn encode(g: &Graphite, p: &Point) -> String {
+        let mut buf = String::new();
+        g.encode_point(&mut buf, p).unwrap();
+        buf
+    }
+
+    #[test]
+    fn can_encode_with_default_template() {
+        let p = point!(
+            "cpu",
+            ("host", "web01")("region", "eu")("dc", "a"),
+            ("idle", 1.5)("busy", 2)("up", true)("name", "x")("value", 3u64);
+            1_500_000_000
+        );
+        assert_eq!(
+            encode(&Graphite::new(), &p),
+            "web01.a.eu.cpu.idle 1.5 1\n\
+             web01.a.eu.cpu.busy 2 1\n\
+             web01.a.eu.cpu.up 1 1\n\
+             web01.a.eu.cpu 3 1\n"
+        );
+    }
+
+    #[test]
+    fn can_encode_with_template() {
+        let p = point!("cpu", ("host", "web01")("region", "eu"), ("idle", 1));
+        let g = Graphite::new()
+            .with_template("region.measurement.host.field")
+            .with_separator("_");
+        assert_eq!(encode(&g, &p), "eu_cpu_web01_idle 1\n");
+
+        let g = Graphite::new().with_template("measurement.region");
+        assert_eq!(encode(&g, &p), "cpu.eu.idle 1\n");
+
+        let g = Graphite::new().with_template("missing.measurement.field");
+        assert_eq!(encode(&g, &p), "cpu.idle 1\n");
+    }
+
+    #[test]
+    fn sanitizes_paths() {
+        let p = point_ref!("c p/u", ("host", "a,b(c)"), ("f*1", 1));
+        let mut buf = String::new();
+        Graphite::new().encode_point_ref(&mut buf, &p).unwrap();
+        assert_eq!(buf, "a_b_c_.c_p_u.f_1 1\n");
+    }
+
+    #[test]
+    fn reports_write_errors() {
+        struct Failing;
+        impl fmt::Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let res = Graphite::new().encode_point(&mut Failing, &point!("m", ("f", 1)));
+        match res {
+            Err(TelegrafError::IoError(e)) => assert!(e.get_ref().unwrap().is::<fmt::Error>()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn skips_string_fields() {
+        let p = point!("m", ("s", "str"));
+        assert_eq!(encode(&Graphite::new(), &p), "");
+    }
+}
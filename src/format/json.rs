@@ -0,0 +1,329 @@
+use std::fmt;
+
+use super::{write_with, Bytes, Serializer};
+use crate::{
+    protocol::{tag_order, AsField, AsTag, FieldDataRef},
+    Point, PointRef, Precision, TelegrafError, Timestamp,
+};
+
+/// Serializer for Telegraf's JSON data format, as read by
+/// inputs with `data_format = "json"`.
+///
+/// Each point is written as an object with `fields`, `name`,
+/// `tags` and `timestamp` keys. Points without a timestamp have
+/// no `timestamp` key. In batch mode, points are wrapped in a
+/// `{"metrics":[...]}` object, matching Telegraf's batch format.
+/// Otherwise every point is written on its own line.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::Json, point};
+///
+/// let p = point!("cpu", ("host", "a"), ("load", 0.5); 1_000_000_000);
+/// let mut buf = String::new();
+/// Json::new().encode_point(&mut buf, &p).unwrap();
+/// assert_eq!(
+///     buf,
+///     "{\"fields\":{\"load\":0.5},\"name\":\"cpu\",\"tags\":{\"host\":\"a\"},\"timestamp\":1}\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json {
+    units: Precision,
+    batch: bool,
+}
+
+impl Default for Json {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Json {
+    /// Creates a serializer writing one point per line with
+    /// timestamps in seconds.
+    pub fn new() -> Self {
+        Self {
+            units: Precision::Seconds,
+            batch: false,
+        }
+    }
+
+    /// Sets the units timestamps are written in. This must match
+    /// `json_timestamp_units` of the Telegraf input.
+    ///
+    /// Defaults to [Precision::Seconds].
+    pub fn with_timestamp_units(mut self, units: Precision) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Sets whether points are wrapped in a `{"metrics":[...]}`
+    /// batch object instead of being written one per line.
+    pub fn with_batch(mut self, batch: bool) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Appends a point.
+    pub fn encode_point<W: fmt::Write>(&self, w: &mut W, pt: &Point) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    /// Appends a borrowed point.
+    pub fn encode_point_ref<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    /// Appends several points, as a single batch object
+    /// in batch mode.
+    pub fn encode_points<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pts: &[Point],
+    ) -> Result<(), TelegrafError> {
+        let pts: Vec<PointRef> = pts.iter().map(PointRef::from).collect();
+        self.write_points(w, &pts)
+    }
+
+    fn write_points<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pts: &[PointRef],
+    ) -> Result<(), TelegrafError> {
+        for p in pts {
+            check_point(&p.fields, p.timestamp, self.units)?;
+        }
+        write_with(w, |w| {
+            if self.batch {
+                w.write_str("{\"metrics\":[")?;
+            }
+            for (i, p) in pts.iter().enumerate() {
+                if self.batch && i > 0 {
+                    w.write_char(',')?;
+                }
+                self.write_object(w, &p.measurement, &p.tags, &p.fields, p.timestamp)?;
+                if !self.batch {
+                    w.write_char('\n')?;
+                }
+            }
+            if self.batch {
+                w.write_str("]}\n")?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_point<W, T, F>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TelegrafError>
+    where
+        W: fmt::Write,
+        T: AsTag,
+        F: AsField,
+    {
+        check_point(fields, timestamp, self.units)?;
+        write_with(w, |w| {
+            if self.batch {
+                w.write_str("{\"metrics\":[")?;
+            }
+            self.write_object(w, measurement, tags, fields, timestamp)?;
+            w.write_str(if self.batch { "]}\n" } else { "\n" })
+        })
+    }
+
+    fn write_object<W, T, F>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+        timestamp: Option<Timestamp>,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+        T: AsTag,
+        F: AsField,
+    {
+        w.write_str("{\"fields\":{")?;
+        for (i, f) in fields.iter().enumerate() {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            write_str(w, f.name())?;
+            w.write_char(':')?;
+            match f.value() {
+                FieldDataRef::Boolean(b) => write!(w, "{}", b)?,
+                FieldDataRef::UNumber(n) => write!(w, "{}", n)?,
+                FieldDataRef::Number(n) => write!(w, "{}", n)?,
+                FieldDataRef::Float(v) => write!(w, "{}", v)?,
+                FieldDataRef::Str(s) => write_str(w, &s)?,
+            }
+        }
+        w.write_str("},\"name\":")?;
+        write_str(w, measurement)?;
+
+        w.write_str(",\"tags\":{")?;
+        let mut sorted: Vec<&T> = tags.iter().collect();
+        sorted.sort_by(|a, b| tag_order(*a, *b));
+        for (i, t) in sorted.into_iter().enumerate() {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            write_str(w, t.name())?;
+            w.write_char(':')?;
+            write_str(w, t.value())?;
+        }
+        w.write_char('}')?;
+
+        if let Some(ts) = timestamp.and_then(|t| t.to_precision(self.units)) {
+            write!(w, ",\"timestamp\":{}", ts)?;
+        }
+        w.write_char('}')
+    }
+}
+
+impl Serializer for Json {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(&mut Bytes(buf), pt)
+    }
+
+    fn serialize_batch(
+        &mut self,
+        buf: &mut Vec<u8>,
+        pts: &[PointRef],
+    ) -> Result<(), TelegrafError> {
+        self.write_points(&mut Bytes(buf), pts)
+    }
+}
+
+/// Checks for values JSON cannot represent before anything is written.
+fn check_point<F: AsField>(
+    fields: &[F],
+    timestamp: Option<Timestamp>,
+    units: Precision,
+) -> Result<(), TelegrafError> {
+    for f in fields {
+        if let FieldDataRef::Float(v) = f.value() {
+            if !v.is_finite() {
+                return Err(TelegrafError::BadProtocol(format!(
+                    "field {:?} is not a finite float",
+                    f.name()
+                )));
+            }
+        }
+    }
+    if let Some(t) = timestamp {
+        if t.to_precision(units).is_none() {
+            return Err(TelegrafError::BadProtocol(format!(
+                "timestamp {} does not fit in {} precision",
+                t.value,
+                units.as_str()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Writes a JSON string literal.
+fn write_str<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    let mut run = 0;
+    for (i, c) in s.char_indices() {
+        let esc = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if (c as u32) < 0x20 => "",
+            _ => continue,
+        };
+        w.write_str(&s[run..i])?;
+        if esc.is_empty() {
+            write!(w, "\\u{:04x}", c as u32)?;
+        } else {
+            w.write_str(esc)?;
+        }
+        run = i + c.len_utf8();
+    }
+    w.write_str(&s[run..])?;
+    w.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref};
+
+    #[test]
+    fn can_encode_point() {
+        let p = point!(
+            "m",
+            ("b", "2")("a", "x\"y"),
+            ("s", "a\nb\u{1}")("i", -1)("u", 2u64)("f", 1.5)("t", true);
+            1_500_000_000
+        );
+        let mut buf = String::new();
+        Json::new()
+            .with_timestamp_units(Precision::Milliseconds)
+            .encode_point(&mut buf, &p)
+            .unwrap();
+        assert_eq!(
+            buf,
+            r#"{"fields":{"s":"a\nb\u0001","i":-1,"u":2,"f":1.5,"t":true},"name":"m","tags":{"a":"x\"y","b":"2"},"timestamp":1500}"#
+                .to_owned()
+                + "\n"
+        );
+    }
+
+    #[test]
+    fn can_encode_without_timestamp() {
+        let mut buf = String::new();
+        Json::new()
+            .encode_point_ref(&mut buf, &point_ref!("m", ("f", 1)))
+            .unwrap();
+        assert_eq!(buf, "{\"fields\":{\"f\":1},\"name\":\"m\",\"tags\":{}}\n");
+    }
+
+    #[test]
+    fn can_encode_batches() {
+        let pts = [point!("a", ("f", 1); 1_000_000_000), point!("b", ("f", 2))];
+        let mut buf = String::new();
+        Json::new().encode_points(&mut buf, &pts).unwrap();
+        assert_eq!(
+            buf,
+            "{\"fields\":{\"f\":1},\"name\":\"a\",\"tags\":{},\"timestamp\":1}\n\
+             {\"fields\":{\"f\":2},\"name\":\"b\",\"tags\":{}}\n"
+        );
+
+        buf.clear();
+        Json::new()
+            .with_batch(true)
+            .encode_points(&mut buf, &pts)
+            .unwrap();
+        assert_eq!(
+            buf,
+            "{\"metrics\":[{\"fields\":{\"f\":1},\"name\":\"a\",\"tags\":{},\"timestamp\":1},\
+             {\"fields\":{\"f\":2},\"name\":\"b\",\"tags\":{}}]}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_non_finite_floats() {
+        let mut buf = String::new();
+        let res = Json::new().encode_point(&mut buf, &point!("m", ("f", f64::NAN)));
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+        assert!(buf.is_empty());
+    }
+}
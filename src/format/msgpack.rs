@@ -0,0 +1,294 @@
+use super::Serializer;
+use crate::{
+    protocol::{tag_order, AsField, AsTag, FieldDataRef},
+    Point, PointRef, Precision, TelegrafError, Timestamp,
+};
+
+/// MessagePack extension type Telegraf uses for timestamps, the
+/// one reserved by the MessagePack spec.
+const TIMESTAMP_EXT: i8 = -1;
+
+/// Serializer for Telegraf's MessagePack data format, as read by
+/// inputs with `data_format = "msgpack"`.
+///
+/// Each point is written as a map with `name`, `time`, `tags` and
+/// `fields` keys, and batches are written as consecutive maps.
+/// Times use the MessagePack timestamp extension, so points need a
+/// timestamp unless the [crate::Client] stamps them with
+/// [crate::Client::with_auto_timestamp].
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::MsgPack, point};
+///
+/// let p = point!("m", ("f", 1); 1_000_000_000);
+/// let mut buf = Vec::new();
+/// MsgPack::new().encode_point(&mut buf, &p).unwrap();
+/// assert_eq!(buf[..6], [0x84, 0xa4, b'n', b'a', b'm', b'e']);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MsgPack {}
+
+impl MsgPack {
+    /// Creates a serializer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a point.
+    pub fn encode_point(&self, buf: &mut Vec<u8>, pt: &Point) -> Result<(), TelegrafError> {
+        self.write_point(buf, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    /// Appends a borrowed point.
+    pub fn encode_point_ref(&self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.write_point(buf, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    fn write_point<T: AsTag, F: AsField>(
+        &self,
+        buf: &mut Vec<u8>,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TelegrafError> {
+        let ts = timestamp
+            .ok_or_else(|| TelegrafError::BadProtocol("msgpack requires a timestamp".to_owned()))?;
+        let nanos = ts.to_precision(Precision::Nanoseconds).ok_or_else(|| {
+            TelegrafError::BadProtocol(format!("timestamp {} is out of range", ts.value))
+        })?;
+
+        write_map_len(buf, 4);
+        write_str(buf, "name");
+        write_str(buf, measurement);
+
+        write_str(buf, "time");
+        write_time(buf, nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32);
+
+        write_str(buf, "tags");
+        let mut sorted: Vec<&T> = tags.iter().collect();
+        sorted.sort_by(|a, b| tag_order(*a, *b));
+        write_map_len(buf, sorted.len());
+        for t in sorted {
+            write_str(buf, t.name());
+            write_str(buf, t.value());
+        }
+
+        write_str(buf, "fields");
+        write_map_len(buf, fields.len());
+        for f in fields {
+            write_str(buf, f.name());
+            match f.value() {
+                FieldDataRef::Boolean(b) => buf.push(if b { 0xc3 } else { 0xc2 }),
+                FieldDataRef::UNumber(n) => write_uint(buf, n),
+                FieldDataRef::Number(n) => write_int(buf, n),
+                FieldDataRef::Float(v) => {
+                    buf.push(0xcb);
+                    buf.extend_from_slice(&v.to_be_bytes());
+                }
+                FieldDataRef::Str(s) => write_str(buf, &s),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serializer for MsgPack {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(buf, pt)
+    }
+}
+
+fn write_map_len(buf: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=15 => buf.push(0x80 | len as u8),
+        16..=0xffff => {
+            buf.push(0xde);
+            buf.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            buf.push(0xdf);
+            buf.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    match len {
+        0..=31 => buf.push(0xa0 | len as u8),
+        32..=0xff => buf.extend_from_slice(&[0xd9, len as u8]),
+        0x100..=0xffff => {
+            buf.push(0xda);
+            buf.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            buf.push(0xdb);
+            buf.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Writes an unsigned integer in its most compact form.
+fn write_uint(buf: &mut Vec<u8>, n: u64) {
+    if n <= 0x7f {
+        buf.push(n as u8);
+    } else if n <= u8::MAX as u64 {
+        buf.extend_from_slice(&[0xcc, n as u8]);
+    } else if n <= u16::MAX as u64 {
+        buf.push(0xcd);
+        buf.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        buf.push(0xce);
+        buf.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        buf.push(0xcf);
+        buf.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// Writes a signed integer in its most compact form.
+fn write_int(buf: &mut Vec<u8>, n: i64) {
+    if n >= 0 {
+        write_uint(buf, n as u64);
+    } else if n >= -32 {
+        buf.push(n as u8);
+    } else if n >= i8::MIN as i64 {
+        buf.extend_from_slice(&[0xd0, n as u8]);
+    } else if n >= i16::MIN as i64 {
+        buf.push(0xd1);
+        buf.extend_from_slice(&(n as i16).to_be_bytes());
+    } else if n >= i32::MIN as i64 {
+        buf.push(0xd2);
+        buf.extend_from_slice(&(n as i32).to_be_bytes());
+    } else {
+        buf.push(0xd3);
+        buf.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// Writes a timestamp extension in the 32, 64 or 96 bit form,
+/// choosing the smallest one as Telegraf does.
+fn write_time(buf: &mut Vec<u8>, secs: u64, nanos: u32) {
+    if secs >> 34 != 0 {
+        buf.extend_from_slice(&[0xc7, 12, TIMESTAMP_EXT as u8]);
+        buf.extend_from_slice(&nanos.to_be_bytes());
+        buf.extend_from_slice(&(secs as i64).to_be_bytes());
+    } else if secs >> 32 != 0 || nanos != 0 {
+        buf.extend_from_slice(&[0xd7, TIMESTAMP_EXT as u8]);
+        buf.extend_from_slice(&(((nanos as u64) << 34) | secs).to_be_bytes());
+    } else {
+        buf.extend_from_slice(&[0xd6, TIMESTAMP_EXT as u8]);
+        buf.extend_from_slice(&(secs as u32).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref};
+    use rmpv::Value;
+    use std::convert::TryInto;
+
+    fn decode(mut buf: &[u8]) -> Vec<Value> {
+        let mut values = Vec::new();
+        while !buf.is_empty() {
+            values.push(rmpv::decode::read_value(&mut buf).unwrap());
+        }
+        values
+    }
+
+    fn get<'a>(v: &'a Value, key: &str) -> &'a Value {
+        v.as_map()
+            .unwrap()
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+            .unwrap()
+    }
+
+    /// Decodes a timestamp extension into seconds and nanoseconds.
+    fn time(v: &Value) -> (u64, u32) {
+        let (ty, data) = v.as_ext().unwrap();
+        assert_eq!(ty, TIMESTAMP_EXT);
+        match data.len() {
+            4 => (u32::from_be_bytes(data.try_into().unwrap()) as u64, 0),
+            8 => {
+                let n = u64::from_be_bytes(data.try_into().unwrap());
+                (n & ((1 << 34) - 1), (n >> 34) as u32)
+            }
+            12 => (
+                i64::from_be_bytes(data[4..].try_into().unwrap()) as u64,
+                u32::from_be_bytes(data[..4].try_into().unwrap()),
+            ),
+            n => panic!("bad timestamp length {}", n),
+        }
+    }
+
+    #[test]
+    fn can_round_trip_points() {
+        let long = "x".repeat(300);
+        let p = point!(
+            "cpu",
+            ("host", "a")("dc", long.as_str()),
+            ("b", true)("u", u64::MAX)("i", -200)("s", "é")("f", 1.5)("n", -1);
+            1_700_000_000_000_000_123
+        );
+        let mut buf = Vec::new();
+        MsgPack::new().encode_point(&mut buf, &p).unwrap();
+        MsgPack::new()
+            .encode_point_ref(&mut buf, &point_ref!("m", ("f", 0); 5_000_000_000))
+            .unwrap();
+
+        let values = decode(&buf);
+        assert_eq!(values.len(), 2);
+        let v = &values[0];
+        let keys: Vec<_> = v
+            .as_map()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            [Some("name"), Some("time"), Some("tags"), Some("fields")]
+        );
+        assert_eq!(get(v, "name").as_str(), Some("cpu"));
+        assert_eq!(time(get(v, "time")), (1_700_000_000, 123));
+
+        let tags = get(v, "tags");
+        assert_eq!(get(tags, "host").as_str(), Some("a"));
+        assert_eq!(get(tags, "dc").as_str(), Some(long.as_str()));
+
+        let fields = get(v, "fields");
+        assert_eq!(get(fields, "b").as_bool(), Some(true));
+        assert_eq!(get(fields, "u").as_u64(), Some(u64::MAX));
+        assert_eq!(get(fields, "i").as_i64(), Some(-200));
+        assert_eq!(get(fields, "s").as_str(), Some("é"));
+        assert_eq!(get(fields, "f").as_f64(), Some(1.5));
+        assert_eq!(get(fields, "n").as_i64(), Some(-1));
+
+        assert_eq!(time(get(&values[1], "time")), (5, 0));
+        assert_eq!(get(&values[1], "tags").as_map().map(Vec::len), Some(0));
+    }
+
+    #[test]
+    fn can_encode_timestamp_forms() {
+        for (secs, nanos, len) in [(1, 0, 4), (1 << 33, 0, 8), (1, 1, 8), (1 << 34, 5, 12)] {
+            let mut buf = Vec::new();
+            write_time(&mut buf, secs, nanos);
+            let v = decode(&buf).remove(0);
+            assert_eq!(v.as_ext().unwrap().1.len(), len);
+            assert_eq!(time(&v), (secs, nanos));
+        }
+    }
+
+    #[test]
+    fn requires_timestamp() {
+        let res = MsgPack::new().encode_point(&mut Vec::new(), &point!("m", ("f", 1)));
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+    }
+}
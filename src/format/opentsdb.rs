@@ -0,0 +1,172 @@
+use std::fmt;
+
+use super::{write_sanitized, write_with, Bytes, Serializer};
+use crate::{
+    protocol::{tag_order, AsField, AsTag, FieldDataRef},
+    Point, PointRef, Precision, TelegrafError, Timestamp,
+};
+
+/// Serializer for the OpenTSDB telnet `put` format, as read by
+/// inputs with `data_format = "opentsdb"`.
+///
+/// Every numeric field of a point is written as its own line:
+///
+/// `put <measurement>.<field> <timestamp> <value> <tag>=<value> ...`
+///
+/// Timestamps are written in seconds and are required, so points
+/// without one are rejected unless the [crate::Client] stamps them
+/// with [crate::Client::with_auto_timestamp]. Characters other than
+/// letters, digits and `-_./` are replaced with `_` in metric names
+/// and tags. Booleans are written as `1` or `0` and string fields
+/// are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::OpenTsdb, point};
+///
+/// let p = point!("cpu", ("host", "web 1"), ("load", 0.5); 1_000_000_000);
+/// let mut buf = String::new();
+/// OpenTsdb::new().encode_point(&mut buf, &p).unwrap();
+/// assert_eq!(buf, "put cpu.load 1 0.5 host=web_1\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenTsdb {
+    separator: String,
+}
+
+impl Default for OpenTsdb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenTsdb {
+    /// Creates a serializer using `.` to separate
+    /// measurement and field names.
+    pub fn new() -> Self {
+        Self {
+            separator: String::from("."),
+        }
+    }
+
+    /// Sets the separator placed between the measurement and
+    /// field names of a metric.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Appends the `put` lines of a point.
+    pub fn encode_point<W: fmt::Write>(&self, w: &mut W, pt: &Point) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    /// Appends the `put` lines of a borrowed point.
+    pub fn encode_point_ref<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields, pt.timestamp)
+    }
+
+    fn write_point<W, T, F>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TelegrafError>
+    where
+        W: fmt::Write,
+        T: AsTag,
+        F: AsField,
+    {
+        let ts = timestamp.ok_or_else(|| {
+            TelegrafError::BadProtocol("opentsdb requires a timestamp".to_owned())
+        })?;
+        let ts = ts.to_precision(Precision::Seconds).ok_or_else(|| {
+            TelegrafError::BadProtocol(format!("timestamp {} is out of range", ts.value))
+        })?;
+
+        let mut sorted: Vec<&T> = tags.iter().collect();
+        sorted.sort_by(|a, b| tag_order(*a, *b));
+
+        write_with(w, |w| {
+            for f in fields {
+                let value = f.value();
+                if let FieldDataRef::Str(_) = value {
+                    continue;
+                }
+                w.write_str("put ")?;
+                write_name(w, measurement)?;
+                w.write_str(&self.separator)?;
+                write_name(w, f.name())?;
+                write!(w, " {} ", ts)?;
+                match value {
+                    FieldDataRef::Boolean(b) => write!(w, "{}", b as u8)?,
+                    FieldDataRef::UNumber(n) => write!(w, "{}", n)?,
+                    FieldDataRef::Number(n) => write!(w, "{}", n)?,
+                    FieldDataRef::Float(v) => write!(w, "{}", v)?,
+                    FieldDataRef::Str(_) => unreachable!(),
+                }
+                for t in &sorted {
+                    w.write_char(' ')?;
+                    write_name(w, t.name())?;
+                    w.write_char('=')?;
+                    write_name(w, t.value())?;
+                }
+                w.write_char('\n')?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Serializer for OpenTsdb {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(&mut Bytes(buf), pt)
+    }
+}
+
+fn write_name<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    write_sanitized(w, s, |c| {
+        c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref};
+
+    #[test]
+    fn can_encode_point() {
+        let p = point!(
+            "sys",
+            ("host", "a")("dc", "eu=1"),
+            ("up", true)("load avg", 1.5)("name", "x")("n", -2);
+            Timestamp::new(1_500, Precision::Milliseconds)
+        );
+        let mut buf = String::new();
+        OpenTsdb::new()
+            .with_separator("_")
+            .encode_point(&mut buf, &p)
+            .unwrap();
+        assert_eq!(
+            buf,
+            "put sys_up 1 1 dc=eu_1 host=a\n\
+             put sys_load_avg 1 1.5 dc=eu_1 host=a\n\
+             put sys_n 1 -2 dc=eu_1 host=a\n"
+        );
+    }
+
+    #[test]
+    fn requires_timestamp() {
+        let mut buf = String::new();
+        let res = OpenTsdb::new().encode_point_ref(&mut buf, &point_ref!("m", ("f", 1)));
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt};
+
+use super::write_with;
+use crate::{FieldData, Point, Precision, TelegrafError};
+
+/// Metric type written in `# TYPE` lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrometheusType {
+    /// `untyped`, for metrics of unknown type.
+    #[default]
+    Untyped,
+    /// `gauge`, for values that can go up and down.
+    Gauge,
+    /// `counter`, for values that only increase.
+    Counter,
+}
+
+/// Encoder for the Prometheus text exposition format, e.g. to
+/// serve the points sent to Telegraf on a `/metrics` page.
+///
+/// Every field becomes a sample named `<measurement>_<field>`, or
+/// just `<measurement>` for fields named `value`, with the tags of
+/// its point as labels. Samples of the same name are grouped under
+/// a single `# TYPE` line. Names and label keys are sanitized to the
+/// characters Prometheus allows, and points with tags whose keys are
+/// the same once sanitized are rejected. Booleans are written as `1`
+/// or `0` and string fields are rejected.
+///
+/// Unlike the formats in [crate::format::Format] this is not written
+/// to a [crate::Client], as Prometheus scrapes rather than receives
+/// metrics.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::Prometheus, point};
+///
+/// let pts = [
+///     point!("http", ("code", "200"), ("requests", 10)),
+///     point!("http", ("code", "500"), ("requests", 1)),
+/// ];
+/// let mut buf = String::new();
+/// Prometheus::new().encode_points(&mut buf, &pts).unwrap();
+/// assert_eq!(
+///     buf,
+///     "# TYPE http_requests untyped\n\
+///      http_requests{code=\"200\"} 10\n\
+///      http_requests{code=\"500\"} 1\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prometheus {
+    metric_type: PrometheusType,
+    timestamps: bool,
+}
+
+impl Prometheus {
+    /// Creates an encoder writing untyped samples without timestamps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the type declared for every metric.
+    ///
+    /// Defaults to [PrometheusType::Untyped].
+    pub fn with_type(mut self, metric_type: PrometheusType) -> Self {
+        self.metric_type = metric_type;
+        self
+    }
+
+    /// Sets whether the timestamps of points are written, in
+    /// milliseconds. Prometheus otherwise uses the scrape time.
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Appends the samples of several points.
+    pub fn encode_points<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pts: &[Point],
+    ) -> Result<(), TelegrafError> {
+        // Samples are grouped by name, in order of first appearance.
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut groups: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+        for (p, pt) in pts.iter().enumerate() {
+            check_labels(pt)?;
+            for (f, field) in pt.fields.iter().enumerate() {
+                if let FieldData::Str(_) = field.value {
+                    return Err(TelegrafError::BadProtocol(format!(
+                        "string field {:?} cannot be written to prometheus",
+                        field.name
+                    )));
+                }
+                let name = metric_name(&pt.measurement, &field.name);
+                let i = *index.entry(name.clone()).or_insert_with(|| {
+                    groups.push((name, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[i].1.push((p, f));
+            }
+        }
+
+        write_with(w, |w| {
+            for (name, samples) in &groups {
+                writeln!(w, "# TYPE {} {}", name, self.type_str())?;
+                for &(p, f) in samples {
+                    self.write_sample(w, name, &pts[p], &pts[p].fields[f].value)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn write_sample<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        name: &str,
+        pt: &Point,
+        value: &FieldData,
+    ) -> fmt::Result {
+        w.write_str(name)?;
+        if !pt.tags.is_empty() {
+            w.write_char('{')?;
+            for (i, t) in pt.tags.iter().enumerate() {
+                if i > 0 {
+                    w.write_char(',')?;
+                }
+                write_label_name(w, &t.name)?;
+                w.write_str("=\"")?;
+                write_label_value(w, &t.value)?;
+                w.write_char('"')?;
+            }
+            w.write_char('}')?;
+        }
+        match value {
+            FieldData::Boolean(b) => write!(w, " {}", *b as u8)?,
+            FieldData::UNumber(n) => write!(w, " {}", n)?,
+            FieldData::Number(n) => write!(w, " {}", n)?,
+            FieldData::Float(v) if v.is_nan() => w.write_str(" NaN")?,
+            FieldData::Float(v) if v.is_infinite() => {
+                w.write_str(if *v > 0.0 { " +Inf" } else { " -Inf" })?
+            }
+            FieldData::Float(v) => write!(w, " {}", v)?,
+            FieldData::Str(_) => unreachable!(),
+        }
+        if self.timestamps {
+            if let Some(ms) = pt
+                .timestamp
+                .and_then(|t| t.to_precision(Precision::Milliseconds))
+            {
+                write!(w, " {}", ms)?;
+            }
+        }
+        w.write_char('\n')
+    }
+
+    fn type_str(&self) -> &'static str {
+        match self.metric_type {
+            PrometheusType::Untyped => "untyped",
+            PrometheusType::Gauge => "gauge",
+            PrometheusType::Counter => "counter",
+        }
+    }
+}
+
+/// Builds a metric name matching `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+fn metric_name(measurement: &str, field: &str) -> String {
+    let mut name = String::with_capacity(measurement.len() + field.len() + 1);
+    name.push_str(measurement);
+    if field != "value" {
+        name.push('_');
+        name.push_str(field);
+    }
+    sanitize(&name, true)
+}
+
+/// Checks that no two tags of a point share a label name.
+fn check_labels(pt: &Point) -> Result<(), TelegrafError> {
+    for (i, a) in pt.tags.iter().enumerate() {
+        let label = sanitize(&a.name, false);
+        if let Some(b) = pt.tags[..i]
+            .iter()
+            .find(|b| sanitize(&b.name, false) == label)
+        {
+            return Err(TelegrafError::BadProtocol(format!(
+                "tags {:?} and {:?} are both written as label {:?}",
+                b.name, a.name, label
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn write_label_name<W: fmt::Write>(w: &mut W, name: &str) -> fmt::Result {
+    w.write_str(&sanitize(name, false))
+}
+
+/// Replaces characters not allowed in names with `_`, prefixing
+/// names that would start with a digit.
+fn sanitize(s: &str, allow_colon: bool) -> String {
+    let mut out = String::with_capacity(s.len() + 1);
+    if s.starts_with(|c: char| c.is_ascii_digit()) || s.is_empty() {
+        out.push('_');
+    }
+    out.extend(s.chars().map(|c| {
+        if c.is_ascii_alphanumeric() || c == '_' || (allow_colon && c == ':') {
+            c
+        } else {
+            '_'
+        }
+    }));
+    out
+}
+
+fn write_label_value<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '\\' => w.write_str("\\\\")?,
+            '"' => w.write_str("\\\"")?,
+            '\n' => w.write_str("\\n")?,
+            c => w.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, Timestamp};
+
+    #[test]
+    fn can_encode_points() {
+        let mut p = point!(
+            "cpu.0",
+            ("host", "a\"b\\c\nd")("1x", "y"),
+            ("value", 1.5)("up", true)
+        );
+        p.timestamp = Some(Timestamp::new(2, Precision::Seconds));
+        let pts = [
+            p,
+            point!("cpu.0", ("value", f64::NEG_INFINITY)("load", 2u64)),
+        ];
+        let mut buf = String::new();
+        Prometheus::new()
+            .with_type(PrometheusType::Gauge)
+            .with_timestamps(true)
+            .encode_points(&mut buf, &pts)
+            .unwrap();
+        assert_eq!(
+            buf,
+            "# TYPE cpu_0 gauge\n\
+             cpu_0{host=\"a\\\"b\\\\c\\nd\",_1x=\"y\"} 1.5 2000\n\
+             cpu_0 -Inf\n\
+             # TYPE cpu_0_up gauge\n\
+             cpu_0_up{host=\"a\\\"b\\\\c\\nd\",_1x=\"y\"} 1 2000\n\
+             # TYPE cpu_0_load gauge\n\
+             cpu_0_load 2\n"
+        );
+    }
+
+    #[test]
+    fn rejects_string_fields() {
+        let mut buf = String::new();
+        let pts = [point!("m", ("f", 1)), point!("m", ("s", "x"))];
+        let res = Prometheus::new().encode_points(&mut buf, &pts);
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn rejects_duplicate_labels() {
+        let mut buf = String::new();
+        let pts = [point!("m", ("a-b", "1")("a_b", "2"), ("f", 1))];
+        let res = Prometheus::new().encode_points(&mut buf, &pts);
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+
+use super::{write_sanitized, write_with, Bytes, Serializer};
+use crate::{
+    protocol::{AsField, AsTag, FieldDataRef},
+    Point, PointRef, TelegrafError,
+};
+
+/// Type of a StatsD metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsdKind {
+    /// Counter, `|c`. Values are added up by the server.
+    Counter,
+    /// Gauge, `|g`, set to the value. Negative values are sent
+    /// as a reset to zero followed by a decrement.
+    Gauge,
+    /// Gauge delta, `|g` with an explicit sign, changing the
+    /// gauge by the value.
+    GaugeDelta,
+    /// Timing in milliseconds, `|ms`.
+    Timing,
+    /// Histogram, `|h`.
+    Histogram,
+    /// Set, `|s`, counting unique values. String fields
+    /// are only written as sets.
+    Set,
+}
+
+/// How the tags of a point are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsdTagStyle {
+    /// Appended to the metric name, e.g. `name,host=a:1|c`, as
+    /// understood by Telegraf's `statsd` input.
+    #[default]
+    Influx,
+    /// Appended after the type, e.g. `name:1|c|#host:a`,
+    /// as used by DogStatsD.
+    DogStatsd,
+}
+
+/// Serializer for the StatsD protocol, as read by Telegraf's
+/// `statsd` input.
+///
+/// Every field of a point is written as its own metric named
+/// `<measurement>.<field>`, or just `<measurement>` for fields named
+/// `value`. Fields are sent as [StatsdKind::Gauge] unless configured
+/// otherwise. Booleans are written as `1` or `0` and timestamps
+/// are dropped, as StatsD has none.
+///
+/// Characters StatsD uses as delimiters are replaced with `_`
+/// in names and tags.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{format::{Statsd, StatsdKind}, point};
+///
+/// let s = Statsd::new()
+///     .with_field_kind("requests", StatsdKind::Counter)
+///     .with_sample_rate(0.5);
+/// let p = point!("http", ("host", "a"), ("requests", 1)("inflight", 3));
+/// let mut buf = String::new();
+/// s.encode_point(&mut buf, &p).unwrap();
+/// assert_eq!(buf, "http.requests,host=a:1|c|@0.5\nhttp.inflight,host=a:3|g\n");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statsd {
+    kind: StatsdKind,
+    field_kinds: Vec<(String, StatsdKind)>,
+    sample_rate: Option<f64>,
+    tag_style: StatsdTagStyle,
+}
+
+impl Default for Statsd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Statsd {
+    /// Creates a serializer writing gauges with Influx-style tags.
+    pub fn new() -> Self {
+        Self {
+            kind: StatsdKind::Gauge,
+            field_kinds: Vec::new(),
+            sample_rate: None,
+            tag_style: StatsdTagStyle::default(),
+        }
+    }
+
+    /// Sets the kind of metric fields are sent as.
+    pub fn with_kind(mut self, kind: StatsdKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets the kind of metric the field with the given
+    /// name is sent as, overriding [Statsd::with_kind].
+    pub fn with_field_kind(mut self, field: impl Into<String>, kind: StatsdKind) -> Self {
+        let field = field.into();
+        self.field_kinds.retain(|(f, _)| *f != field);
+        self.field_kinds.push((field, kind));
+        self
+    }
+
+    /// Sets the sample rate reported with counters, timings and
+    /// histograms, between 0 and 1. The server scales values up by
+    /// its inverse, so the caller is expected to only send that
+    /// fraction of metrics.
+    pub fn with_sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = Some(rate);
+        self
+    }
+
+    /// Sets how tags are written.
+    ///
+    /// Defaults to [StatsdTagStyle::Influx].
+    pub fn with_tag_style(mut self, style: StatsdTagStyle) -> Self {
+        self.tag_style = style;
+        self
+    }
+
+    /// Appends the StatsD metrics of a point.
+    pub fn encode_point<W: fmt::Write>(&self, w: &mut W, pt: &Point) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields)
+    }
+
+    /// Appends the StatsD metrics of a borrowed point.
+    pub fn encode_point_ref<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        self.write_point(w, &pt.measurement, &pt.tags, &pt.fields)
+    }
+
+    fn write_point<W, T, F>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        fields: &[F],
+    ) -> Result<(), TelegrafError>
+    where
+        W: fmt::Write,
+        T: AsTag,
+        F: AsField,
+    {
+        if let Some(rate) = self.sample_rate {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err(TelegrafError::BadProtocol(format!(
+                    "sample rate {} is not between 0 and 1",
+                    rate
+                )));
+            }
+        }
+
+        write_with(w, |w| {
+            for f in fields {
+                let kind = self.kind_of(f.name());
+                let value = f.value();
+                match (kind, &value) {
+                    (StatsdKind::Set, _) => {}
+                    (_, FieldDataRef::Str(_)) => continue,
+                    (StatsdKind::Gauge, FieldDataRef::Number(n)) if *n < 0 => {
+                        self.write_metric(w, measurement, tags, f.name(), kind, "0")?;
+                    }
+                    (StatsdKind::Gauge, FieldDataRef::Float(v)) if *v < 0.0 => {
+                        self.write_metric(w, measurement, tags, f.name(), kind, "0")?;
+                    }
+                    _ => {}
+                }
+                let sign = match (kind, &value) {
+                    (StatsdKind::GaugeDelta, FieldDataRef::Number(n)) if *n < 0 => "",
+                    (StatsdKind::GaugeDelta, FieldDataRef::Float(v)) if *v < 0.0 => "",
+                    (StatsdKind::GaugeDelta, _) => "+",
+                    _ => "",
+                };
+                let value = Value { sign, value };
+                self.write_metric(w, measurement, tags, f.name(), kind, value)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn kind_of(&self, field: &str) -> StatsdKind {
+        self.field_kinds
+            .iter()
+            .find(|(f, _)| f == field)
+            .map(|(_, k)| *k)
+            .unwrap_or(self.kind)
+    }
+
+    fn write_metric<W, T, V>(
+        &self,
+        w: &mut W,
+        measurement: &str,
+        tags: &[T],
+        field: &str,
+        kind: StatsdKind,
+        value: V,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+        T: AsTag,
+        V: fmt::Display,
+    {
+        write_name(w, measurement)?;
+        if field != "value" {
+            w.write_char('.')?;
+            write_name(w, field)?;
+        }
+        if self.tag_style == StatsdTagStyle::Influx {
+            for t in tags {
+                w.write_char(',')?;
+                write_name(w, t.name())?;
+                w.write_char('=')?;
+                write_name(w, t.value())?;
+            }
+        }
+
+        let suffix = match kind {
+            StatsdKind::Counter => "c",
+            StatsdKind::Gauge | StatsdKind::GaugeDelta => "g",
+            StatsdKind::Timing => "ms",
+            StatsdKind::Histogram => "h",
+            StatsdKind::Set => "s",
+        };
+        write!(w, ":{}|{}", value, suffix)?;
+
+        let sampled = matches!(
+            kind,
+            StatsdKind::Counter | StatsdKind::Timing | StatsdKind::Histogram
+        );
+        if let Some(rate) = self.sample_rate.filter(|r| sampled && *r < 1.0) {
+            write!(w, "|@{}", rate)?;
+        }
+
+        if self.tag_style == StatsdTagStyle::DogStatsd && !tags.is_empty() {
+            w.write_str("|#")?;
+            for (i, t) in tags.iter().enumerate() {
+                if i > 0 {
+                    w.write_char(',')?;
+                }
+                write_name(w, t.name())?;
+                w.write_char(':')?;
+                write_name(w, t.value())?;
+            }
+        }
+        w.write_char('\n')
+    }
+}
+
+impl Serializer for Statsd {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(&mut Bytes(buf), pt)
+    }
+}
+
+/// Field value with an optional explicit sign.
+struct Value<'a> {
+    sign: &'static str,
+    value: FieldDataRef<'a>,
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.sign)?;
+        match &self.value {
+            FieldDataRef::Boolean(b) => write!(f, "{}", *b as u8),
+            FieldDataRef::UNumber(n) => write!(f, "{}", n),
+            FieldDataRef::Number(n) => write!(f, "{}", n),
+            FieldDataRef::Float(v) => write!(f, "{}", v),
+            FieldDataRef::Str(s) => write_name(f, s),
+        }
+    }
+}
+
+fn write_name<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    write_sanitized(w, s, |c| {
+        !matches!(c, ':' | '|' | '@' | '#' | ',' | '=' | ' ' | '\n' | '\r')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref};
+
+    fn encode(s: &Statsd, p: &Point) -> String {
+        let mut buf = String::new();
+        s.encode_point(&mut buf, p).unwrap();
+        buf
+    }
+
+    #[test]
+    fn can_encode_kinds() {
+        let p = point!(
+            "m",
+            ("c", 1)("t", 2.5)("h", 3u64)("s", "u:1")("value", true)
+        );
+        let s = Statsd::new()
+            .with_kind(StatsdKind::Counter)
+            .with_field_kind("t", StatsdKind::Timing)
+            .with_field_kind("h", StatsdKind::Histogram)
+            .with_field_kind("s", StatsdKind::Set)
+            .with_field_kind("value", StatsdKind::Gauge);
+        assert_eq!(
+            encode(&s, &p),
+            "m.c:1|c\nm.t:2.5|ms\nm.h:3|h\nm.s:u_1|s\nm:1|g\n"
+        );
+    }
+
+    #[test]
+    fn can_encode_gauges() {
+        let p = point!("m", ("a", 5)("b", -5)("c", -1.5));
+        assert_eq!(
+            encode(&Statsd::new(), &p),
+            "m.a:5|g\nm.b:0|g\nm.b:-5|g\nm.c:0|g\nm.c:-1.5|g\n"
+        );
+        let s = Statsd::new().with_kind(StatsdKind::GaugeDelta);
+        assert_eq!(encode(&s, &p), "m.a:+5|g\nm.b:-5|g\nm.c:-1.5|g\n");
+    }
+
+    #[test]
+    fn can_encode_sample_rates() {
+        let p = point!("m", ("c", 1)("g", 1));
+        let s = Statsd::new()
+            .with_field_kind("c", StatsdKind::Counter)
+            .with_sample_rate(0.1);
+        assert_eq!(encode(&s, &p), "m.c:1|c|@0.1\nm.g:1|g\n");
+
+        let s = s.with_sample_rate(1.0);
+        assert_eq!(encode(&s, &p), "m.c:1|c\nm.g:1|g\n");
+
+        let s = s.with_sample_rate(0.0);
+        assert!(s.encode_point(&mut String::new(), &p).is_err());
+    }
+
+    #[test]
+    fn can_encode_tag_styles() {
+        let p = point_ref!("m", ("host", "a:b")("dc", "eu"), ("f", 1));
+        let mut buf = String::new();
+        Statsd::new().encode_point_ref(&mut buf, &p).unwrap();
+        Statsd::new()
+            .with_tag_style(StatsdTagStyle::DogStatsd)
+            .with_kind(StatsdKind::Counter)
+            .with_sample_rate(0.5)
+            .encode_point_ref(&mut buf, &p)
+            .unwrap();
+        assert_eq!(
+            buf,
+            "m.f,host=a_b,dc=eu:1|g\nm.f:1|c|@0.5|#host:a_b,dc:eu\n"
+        );
+    }
+
+    #[test]
+    fn skips_strings_unless_set() {
+        let p = point!("m", ("s", "x"));
+        assert_eq!(encode(&Statsd::new(), &p), "");
+    }
+}
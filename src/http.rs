@@ -0,0 +1,823 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use url::Url;
+
+use crate::{Precision, TelegrafError};
+
+/// Time allowed for connecting, sending a batch
+/// or waiting for the response.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Non-2xx response from an HTTP write endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpError {
+    /// HTTP status code.
+    pub status: u16,
+    /// Error code of InfluxDB v2 JSON errors, e.g. `invalid`.
+    pub code: Option<String>,
+    /// Error message of InfluxDB JSON errors, otherwise
+    /// the response body or reason phrase.
+    pub message: String,
+    /// Delay requested by a `Retry-After` header, sent
+    /// with 429 and 503 responses.
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    /// Whether the write may succeed if sent again later,
+    /// i.e. the status is 429 or a server error.
+    pub fn is_retryable(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http status {}", self.status)?;
+        if let Some(code) = &self.code {
+            write!(f, " ({})", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Connection POSTing batches to an HTTP write endpoint,
+/// kept alive between writes.
+pub(crate) struct HttpConnector {
+    addrs: Vec<SocketAddr>,
+    /// Value of the `Host` header.
+    host: String,
+    /// Path and query of the request.
+    target: String,
+    /// Whether the query is for the InfluxDB v2 API.
+    v2: bool,
+    /// Value of the `Authorization` header.
+    auth: Option<String>,
+    precision: Option<Precision>,
+    #[cfg(feature = "tls")]
+    tls: Option<crate::tls::TlsConnector>,
+    stream: Option<BufReader<Stream>>,
+}
+
+/// Plaintext or TLS socket.
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<crate::tls::TlsStream>),
+}
+
+/// Status and relevant parts of a response.
+struct Response {
+    status: u16,
+    reason: String,
+    body: Vec<u8>,
+    influx_error: Option<String>,
+    retry_after: Option<Duration>,
+    close: bool,
+}
+
+impl HttpConnector {
+    /// Creates a connector from a URL. The connection is
+    /// established on the first write.
+    ///
+    /// `token` is sent as an InfluxDB v2 `Authorization: Token`
+    /// header and credentials in the URL as basic auth; all other
+    /// query parameters are passed through. Without a path, writes
+    /// go to `/api/v2/write` if a `bucket` is given, `/write` if a
+    /// `db` is, and to `/telegraf`, the default path of Telegraf's
+    /// `http_listener_v2`, otherwise.
+    pub(crate) fn new(u: &Url) -> Result<Self, TelegrafError> {
+        let addrs = u.socket_addrs(|| u.port_or_known_default())?;
+        let host = match (u.host_str(), u.port()) {
+            (Some(h), Some(p)) => format!("{}:{}", h, p),
+            (Some(h), None) => h.to_owned(),
+            (None, _) => {
+                return Err(TelegrafError::BadProtocol(format!(
+                    "missing host in URL {}",
+                    u
+                )))
+            }
+        };
+
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        let mut token = None;
+        let mut precision = None;
+        let (mut v1, mut v2) = (false, false);
+        for (k, v) in u.query_pairs() {
+            match &*k {
+                "token" => {
+                    token = Some(v.into_owned());
+                    continue;
+                }
+                "db" => v1 = true,
+                "bucket" => v2 = true,
+                "precision" => precision = Some(parse_precision(&v)?),
+                _ => {}
+            }
+            query.append_pair(&k, &v);
+        }
+        let query = query.finish();
+
+        let path = match u.path() {
+            "" | "/" if v2 => "/api/v2/write",
+            "" | "/" if v1 => "/write",
+            "" | "/" => "/telegraf",
+            p => p,
+        };
+        let target = if query.is_empty() {
+            path.to_owned()
+        } else {
+            format!("{}?{}", path, query)
+        };
+
+        let auth = match token {
+            Some(t) => Some(format!("Token {}", t)),
+            None if !u.username().is_empty() => {
+                let creds = format!(
+                    "{}:{}",
+                    percent_decode(u.username()),
+                    percent_decode(u.password().unwrap_or_default())
+                );
+                Some(format!("Basic {}", base64(creds.as_bytes())))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            addrs,
+            host,
+            target,
+            v2,
+            auth,
+            precision,
+            #[cfg(feature = "tls")]
+            tls: None,
+            stream: None,
+        })
+    }
+
+    /// Sends requests over TLS, for `https://` URLs.
+    #[cfg(feature = "tls")]
+    pub(crate) fn with_tls(mut self, tls: crate::tls::TlsConnector) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Precision given by the `precision` query parameter.
+    pub(crate) fn precision(&self) -> Option<Precision> {
+        self.precision
+    }
+
+    /// Sets the `precision` query parameter, so the endpoint reads
+    /// timestamps in the precision they are written in.
+    pub(crate) fn set_precision(&mut self, precision: Precision) {
+        let value = match precision {
+            // The v1 API only knows `u`.
+            Precision::Microseconds if !self.v2 => "u",
+            p => p.as_str(),
+        };
+        let (path, query) = self.target.split_once('?').unwrap_or((&self.target, ""));
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(
+                url::form_urlencoded::parse(query.as_bytes()).filter(|(k, _)| k != "precision"),
+            )
+            .append_pair("precision", value)
+            .finish();
+        self.target = format!("{}?{}", path, query);
+        self.precision = Some(precision);
+    }
+
+    /// POSTs a batch, failing with [TelegrafError::HttpError]
+    /// on non-2xx responses.
+    pub(crate) fn write(&mut self, body: &[u8]) -> Result<(), TelegrafError> {
+        let reused = self.stream.is_some();
+        let res = match self.round_trip(body) {
+            // The server may have closed a kept alive connection
+            // while it was idle, so retry once on a new one. Other
+            // failures may come after the request was processed,
+            // and retrying them could write the points twice.
+            Err(RoundTripError {
+                retryable: true, ..
+            }) if reused => {
+                self.stream = None;
+                self.round_trip(body)
+            }
+            res => res,
+        };
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                self.stream = None;
+                return Err(e.error.into());
+            }
+        };
+        if res.close {
+            self.stream = None;
+        }
+        if (200..300).contains(&res.status) {
+            return Ok(());
+        }
+        Err(TelegrafError::HttpError(res.into_error()))
+    }
+
+    pub(crate) fn close(&self) -> io::Result<()> {
+        match &self.stream {
+            Some(s) => s.get_ref().shutdown(),
+            None => Ok(()),
+        }
+    }
+
+    fn round_trip(&mut self, body: &[u8]) -> Result<Response, RoundTripError> {
+        let stale = self
+            .stream
+            .as_ref()
+            .is_some_and(|s| !s.buffer().is_empty() || s.get_ref().is_closed());
+        if stale {
+            self.stream = None;
+        }
+        if self.stream.is_none() {
+            let stream = self.open().map_err(RoundTripError::fatal)?;
+            self.stream = Some(BufReader::new(stream));
+        }
+        let stream = self.stream.as_mut().unwrap();
+
+        let mut head = format!(
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             User-Agent: telegraf-rust/{}\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: {}\r\n",
+            self.target,
+            self.host,
+            env!("CARGO_PKG_VERSION"),
+            body.len()
+        );
+        if let Some(auth) = &self.auth {
+            head.push_str("Authorization: ");
+            head.push_str(auth);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+
+        let w = stream.get_mut();
+        w.write_all(head.as_bytes())
+            .and_then(|_| w.write_all(body))
+            .and_then(|_| w.flush())
+            .map_err(RoundTripError::retryable)?;
+        // A connection closed without any response
+        // never got to process the request.
+        if stream.fill_buf().map_err(RoundTripError::fatal)?.is_empty() {
+            return Err(RoundTripError::retryable(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before response",
+            )));
+        }
+        read_response(stream).map_err(RoundTripError::fatal)
+    }
+
+    fn open(&self) -> io::Result<Stream> {
+        let sock = connect(&self.addrs)?;
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Ok(Stream::Tls(Box::new(tls.connect(sock)?)));
+        }
+        Ok(Stream::Tcp(sock))
+    }
+}
+
+/// Failure of a request, noting whether it is
+/// known not to have reached the server.
+struct RoundTripError {
+    error: io::Error,
+    retryable: bool,
+}
+
+impl RoundTripError {
+    fn retryable(error: io::Error) -> Self {
+        Self {
+            error,
+            retryable: true,
+        }
+    }
+
+    fn fatal(error: io::Error) -> Self {
+        Self {
+            error,
+            retryable: false,
+        }
+    }
+}
+
+impl Stream {
+    fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.shutdown(Shutdown::Both),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.close(),
+        }
+    }
+
+    fn sock(&self) -> &TcpStream {
+        match self {
+            Stream::Tcp(s) => s,
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.sock(),
+        }
+    }
+
+    /// Whether an idle connection was closed, or sent
+    /// something, and so can't take another request.
+    fn is_closed(&self) -> bool {
+        let sock = self.sock();
+        if sock.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let res = sock.peek(&mut [0]);
+        let _ = sock.set_nonblocking(false);
+        !matches!(res, Err(e) if e.kind() == io::ErrorKind::WouldBlock)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+impl Response {
+    fn into_error(self) -> HttpError {
+        let Response {
+            status,
+            reason,
+            body,
+            influx_error,
+            retry_after,
+            ..
+        } = self;
+        let body = String::from_utf8_lossy(&body);
+        let message = json_str(&body, "message")
+            .or_else(|| json_str(&body, "error"))
+            .or(influx_error)
+            .unwrap_or_else(|| match body.trim() {
+                "" => reason,
+                b => b.to_owned(),
+            });
+        HttpError {
+            status,
+            code: json_str(&body, "code"),
+            message,
+            retry_after,
+        }
+    }
+}
+
+fn connect(addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+    let mut err = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, TIMEOUT) {
+            Ok(s) => {
+                s.set_read_timeout(Some(TIMEOUT))?;
+                s.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(s);
+            }
+            Err(e) => err = Some(e),
+        }
+    }
+    Err(err.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to")))
+}
+
+fn read_response<R: BufRead>(r: &mut R) -> io::Result<Response> {
+    let mut line = String::new();
+    if r.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before response",
+        ));
+    }
+    let mut parts = line.trim_end().splitn(3, ' ');
+    let status = match (parts.next(), parts.next()) {
+        (Some(v), Some(s)) if v.starts_with("HTTP/1.") => s.parse::<u16>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| invalid_data(format!("bad status line {:?}", line.trim_end())))?;
+    let reason = parts.next().unwrap_or_default().to_owned();
+
+    let mut len = None;
+    let mut chunked = false;
+    let mut close = line.starts_with("HTTP/1.0");
+    let mut influx_error = None;
+    let mut retry_after = None;
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            return Err(invalid_data("truncated response headers".to_owned()));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = match header.split_once(':') {
+            Some((n, v)) => (n.trim().to_ascii_lowercase(), v.trim()),
+            None => continue,
+        };
+        match &*name {
+            "content-length" => {
+                len = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| invalid_data(format!("bad content length {:?}", value)))?,
+                )
+            }
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "connection" => close = value.eq_ignore_ascii_case("close"),
+            "retry-after" => retry_after = value.parse().ok().map(Duration::from_secs),
+            "x-influxdb-error" => influx_error = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    let mut body = Vec::new();
+    if status == 204 || status == 304 || status < 200 {
+        // No body.
+    } else if chunked {
+        read_chunked(r, &mut body)?;
+    } else if let Some(len) = len {
+        r.take(len).read_to_end(&mut body)?;
+    } else {
+        r.read_to_end(&mut body)?;
+        close = true;
+    }
+
+    Ok(Response {
+        status,
+        reason,
+        body,
+        influx_error,
+        retry_after,
+        close,
+    })
+}
+
+fn read_chunked<R: BufRead>(r: &mut R, body: &mut Vec<u8>) -> io::Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        r.read_line(&mut line)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16)
+            .map_err(|_| invalid_data(format!("bad chunk size {:?}", size)))?;
+        if size == 0 {
+            // Skip trailers.
+            loop {
+                line.clear();
+                if r.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+        r.take(size).read_to_end(body)?;
+        line.clear();
+        r.read_line(&mut line)?;
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parses the precision parameter of both the v1 and v2 APIs.
+fn parse_precision(s: &str) -> Result<Precision, TelegrafError> {
+    match s {
+        "n" | "ns" => Ok(Precision::Nanoseconds),
+        "u" | "us" => Ok(Precision::Microseconds),
+        "ms" => Ok(Precision::Milliseconds),
+        "s" => Ok(Precision::Seconds),
+        _ => Err(TelegrafError::BadProtocol(format!(
+            "unsupported precision {:?}",
+            s
+        ))),
+    }
+}
+
+/// Finds the string value of a key in a JSON error body. This is
+/// not a JSON parser, just enough for the flat objects InfluxDB
+/// returns.
+fn json_str(body: &str, key: &str) -> Option<String> {
+    let pat = format!("\"{}\"", key);
+    let mut rest = body;
+    while let Some(i) = rest.find(&pat) {
+        rest = &rest[i + pat.len()..];
+        let value = match rest.trim_start().strip_prefix(':') {
+            Some(v) => v.trim_start(),
+            None => continue,
+        };
+        let mut chars = match value.strip_prefix('"') {
+            Some(v) => v.chars(),
+            None => continue,
+        };
+        let mut out = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(out),
+                '\\' => match chars.next()? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        out.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+        return None;
+    }
+    None
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, Client};
+    use std::{net::TcpListener, thread};
+
+    /// Serves the given responses in turn on one connection,
+    /// returning the requests received.
+    fn serve(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (conn, _) = listener.accept().unwrap();
+            let mut r = BufReader::new(conn);
+            let mut requests = Vec::new();
+            for resp in responses {
+                requests.push(read_request(&mut r));
+                r.get_mut().write_all(resp.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (addr, handle)
+    }
+
+    fn read_request(r: &mut BufReader<TcpStream>) -> String {
+        let mut req = String::new();
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            r.read_line(&mut line).unwrap();
+            if let Some(v) = line.strip_prefix("Content-Length: ") {
+                len = v.trim().parse().unwrap();
+            }
+            req.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        r.read_exact(&mut body).unwrap();
+        req.push_str(std::str::from_utf8(&body).unwrap());
+        req
+    }
+
+    #[test]
+    fn can_write_v1() {
+        let (addr, server) = serve(vec![
+            "HTTP/1.1 204 No Content\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\n\r\n",
+        ]);
+        let url = format!("http://us%40r:pw@{}/?db=metrics&rp=week&precision=s", addr);
+        let mut c = Client::new(&url).unwrap();
+        c.write_point(&point!("m", ("f", 1); 2_000_000_000))
+            .unwrap();
+        c.write_points(&[point!("m", ("f", 2)), point!("m", ("f", 3))])
+            .unwrap();
+        c.close().unwrap();
+
+        let reqs = server.join().unwrap();
+        assert_eq!(
+            reqs[0],
+            format!(
+                "POST /write?db=metrics&rp=week&precision=s HTTP/1.1\r\n\
+                 Host: {}\r\n\
+                 User-Agent: telegraf-rust/{}\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Length: 9\r\n\
+                 Authorization: Basic {}\r\n\
+                 \r\n\
+                 m f=1i 2\n",
+                addr,
+                env!("CARGO_PKG_VERSION"),
+                base64(b"us@r:pw")
+            )
+        );
+        assert!(reqs[1].ends_with("\r\n\r\nm f=2i\nm f=3i\n"));
+    }
+
+    #[test]
+    fn can_write_v2() {
+        let (addr, server) = serve(vec![
+            "HTTP/1.1 400 Bad Request\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: 50\r\n\r\n\
+             {\"code\":\"invalid\",\"message\":\"unable to \\\"parse\\\"\"}",
+        ]);
+        let url = format!("http://{}?org=acme&bucket=b&token=s3cr3t", addr);
+        let mut c = Client::new(&url).unwrap();
+        let err = c.write_point(&point!("m", ("f", 1))).unwrap_err();
+        match err {
+            TelegrafError::HttpError(e) => {
+                assert_eq!(e.status, 400);
+                assert_eq!(e.code.as_deref(), Some("invalid"));
+                assert_eq!(e.message, "unable to \"parse\"");
+                assert!(!e.is_retryable());
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        let reqs = server.join().unwrap();
+        assert!(reqs[0].starts_with("POST /api/v2/write?org=acme&bucket=b HTTP/1.1\r\n"));
+        assert!(reqs[0].contains("\r\nAuthorization: Token s3cr3t\r\n"));
+    }
+
+    #[test]
+    fn can_read_error_responses() {
+        let (addr, server) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\n\
+             Retry-After: 5\r\n\
+             Transfer-Encoding: chunked\r\n\r\n\
+             5\r\nbusy \r\n3;x=y\r\nnow\r\n0\r\n\r\n",
+            "HTTP/1.1 404 Not Found\r\n\
+             X-Influxdb-Error: database not found\r\n\
+             Content-Length: 0\r\n\r\n",
+            "HTTP/1.0 413 Request Entity Too Large\r\n\r\n",
+        ]);
+        let mut c = HttpConnector::new(&Url::parse(&format!("http://{}", addr)).unwrap()).unwrap();
+        let errs: Vec<HttpError> = (0..3)
+            .map(|_| match c.write(b"m f=1i\n") {
+                Err(TelegrafError::HttpError(e)) => e,
+                r => panic!("unexpected result {:?}", r),
+            })
+            .collect();
+        assert_eq!(errs[0].message, "busy now");
+        assert_eq!(errs[0].retry_after, Some(Duration::from_secs(5)));
+        assert!(errs[0].is_retryable());
+        assert_eq!(errs[1].message, "database not found");
+        assert_eq!(errs[2].message, "Request Entity Too Large");
+
+        let reqs = server.join().unwrap();
+        assert!(reqs[0].starts_with("POST /telegraf HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn does_not_resend_received_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let (closed_tx, closed_rx) = std::sync::mpsc::channel();
+        let server = thread::spawn(move || {
+            // Closes a kept alive connection, then fails
+            // after the next request was received.
+            for resp in [
+                "HTTP/1.1 204 No Content\r\n\r\n",
+                "HTTP/1.1 204 No Content\r\n",
+            ] {
+                let mut r = BufReader::new(listener.accept().unwrap().0);
+                read_request(&mut r);
+                r.get_mut().write_all(resp.as_bytes()).unwrap();
+                drop(r);
+                closed_tx.send(()).unwrap();
+            }
+            listener
+        });
+
+        let mut c = HttpConnector::new(&url).unwrap();
+        c.write(b"m f=1i\n").unwrap();
+        closed_rx.recv().unwrap();
+        match c.write(b"m f=2i\n") {
+            Err(TelegrafError::ConnectionError(e)) => assert!(e.contains("truncated")),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let listener = server.join().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert_eq!(
+            listener.accept().unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+    }
+
+    #[test]
+    fn sends_precision_of_client() {
+        let (addr, server) = serve(vec!["HTTP/1.1 204 No Content\r\n\r\n"]);
+        let url = format!("http://{}/write?db=a&precision=s", addr);
+        let mut c = Client::new(&url)
+            .unwrap()
+            .with_precision(Precision::Milliseconds);
+        c.write_point(&point!("m", ("f", 1); 2_000_000_000))
+            .unwrap();
+        let req = &server.join().unwrap()[0];
+        assert!(req.starts_with("POST /write?db=a&precision=ms HTTP/1.1\r\n"));
+        assert!(req.ends_with("\r\n\r\nm f=1i 2000\n"));
+
+        let (addr, server) = serve(vec!["HTTP/1.1 204 No Content\r\n\r\n"]);
+        let url = format!("http://{}/api/v2/write?bucket=b", addr);
+        let mut c = Client::new(&url)
+            .unwrap()
+            .with_precision(Precision::Microseconds);
+        c.write_point(&point!("m", ("f", 1); 2_000_000_000))
+            .unwrap();
+        let req = &server.join().unwrap()[0];
+        assert!(req.starts_with("POST /api/v2/write?bucket=b&precision=us HTTP/1.1\r\n"));
+        assert!(req.ends_with("\r\n\r\nm f=1i 2000000\n"));
+    }
+
+    #[test]
+    fn rejects_bad_precision() {
+        let res = Client::new("http://localhost:8086/write?db=a&precision=h");
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+    }
+
+    #[test]
+    fn can_encode_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"user:pass"), "dXNlcjpwYXNz");
+    }
+}
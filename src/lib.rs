@@ -116,21 +116,45 @@
 //!
 //! Out of the box implementations are provided for many common data types, but manual implementation is possible for other data types.
 //!
+//! Unsigned integers are written with a `u` suffix, which InfluxDB 1.x and Telegraf without `influx_uint_support` reject.
+//! For those, configure the `Client` to write them as signed integers instead:
+//!
+//! ```rust,no_run
+//! use telegraf::*;
+//!
+//! let mut client = Client::new("tcp://localhost:8094").unwrap().with_unsigned_mode(UnsignedMode::SignedSaturating);
+//! ```
+//!
+//! ### Tag and field order
+//!
+//! Tags are written sorted by key, as recommended by InfluxDB. Fields are written in the order they were added,
+//! unless the client is configured with [Client::with_field_order]. Points compare and hash equal
+//! regardless of the order their tags and fields were added in, so they can be deduplicated in a `HashSet`.
+//!
+//! To group points by series (measurement and tag set), use [Point::series_key], which returns a `SeriesKey` that
+//! implements `Hash`, `Eq` and `Ord`.
+//!
 //! ### Timestamps
 //!
-//! Timestamps are an optional filed, if not present the Telegraf daemon will set the timestamp using the current time.
-//! Timestamps are specified in nanosecond-precision Unix time, therefore `u64` must implement the `From<T>` trait for the field type, if the implementation is not already present:
+//! Timestamps are an optional field, if not present the Telegraf daemon will set the timestamp using the current time.
+//! Any attribute used as a timestamp must implement the `IntoTimestamp` trait provided by this library.
+//! Implementations are provided for `u64`, `std::time::SystemTime` and `std::time::Duration` (time since the Unix epoch),
+//! as well as `chrono::DateTime` and `time::OffsetDateTime` behind the `chrono` and `time` cargo features. Derived
+//! metrics also accept any other type implementing `Into<u64>`, such as `u32`.
+//! Times before the Unix epoch cannot be written and are reported as a `TelegrafError::BadTimestamp` by `Client::write`
+//! and `Metric::try_to_point`. `Metric::to_point` and the `point` macros leave such timestamps unset instead, so Telegraf
+//! stamps the point when it arrives.
+//! Other types can implement the trait manually:
 //!
 //! ```rust
 //! use telegraf::*;
 //!
-//! #[derive(Copy, Clone)]
 //! struct MyType {
 //!     // ...
 //! }
 //!
-//! impl From<MyType> for u64 {
-//!     fn from(my_type: MyType) -> Self {
+//! impl IntoTimestamp for MyType {
+//!     fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
 //!         todo!()
 //!     }
 //! }
@@ -144,15 +168,253 @@
 //!
 //! ```
 //!
+//! By default timestamps are interpreted as nanoseconds. A different unit can be declared
+//! per point with a `Timestamp` and `Precision`, or on a derived struct via the `precision` argument:
+//!
+//! ```rust
+//! use telegraf::*;
+//!
+//! #[derive(Metric)]
+//! struct MyMetric {
+//!     #[telegraf(timestamp, precision = "ms")]
+//!     ts: u64,
+//!     field1: i32,
+//! }
+//!
+//! let p = point!("measurement", ("field1", 1); Timestamp::new(1_600_000_000_000, Precision::Milliseconds));
+//! ```
+//!
+//! Timestamps are converted when written, so they always match the precision configured on the `Client`
+//! (nanoseconds unless set otherwise). This should match the `precision` of your Telegraf listener:
+//!
+//! ```rust,no_run
+//! use telegraf::*;
+//!
+//! let mut client = Client::new("tcp://localhost:8094").unwrap().with_precision(Precision::Seconds);
+//! ```
+//!
+//! To avoid points being stamped on arrival, e.g. when batching, the `Client` can instead stamp points
+//! that have no timestamp with the current time when they are written:
+//!
+//! ```rust,no_run
+//! use telegraf::*;
+//!
+//! let mut client = Client::new("tcp://localhost:8094").unwrap().with_auto_timestamp(Precision::Milliseconds);
+//! ```
+//!
 //! More information about timestamps can be found [here](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_tutorial/#timestamp).
+//!
+//! ## Build points with [crate::PointBuilder]
+//!
+//! When tags or fields are only known at runtime, points can be assembled with a builder. Validation
+//! is applied when the point is built:
+//!
+//! ```rust,no_run
+//! use telegraf::{Client, Point};
+//!
+//! let mut c = Client::new("tcp://localhost:8094").unwrap();
+//!
+//! let mut builder = Point::builder("measurement")
+//!     .tag("tag1", "tag1value")
+//!     .field("field1", 10);
+//! if let Ok(v) = std::env::var("HOSTNAME") {
+//!     builder = builder.tag("host", v);
+//! }
+//! let p = builder.build().unwrap();
+//!
+//! c.write_point(&p);
+//! ```
+//!
+//! ## Borrowed points with [crate::point_ref]
+//!
+//! `Point` owns every string it contains. In hot paths, a [crate::PointRef] can be written instead, which borrows
+//! its measurement, tags and fields. The `point_ref` macro takes the same syntax as `point` and builds one
+//! without heap allocation:
+//!
+//! ```rust,no_run
+//! use telegraf::{point_ref, Client};
+//!
+//! let mut c = Client::new("tcp://localhost:8094").unwrap();
+//!
+//! let host = String::from("web01");
+//! let p = point_ref!("cpu", ("host", host.as_str()), ("load", 0.5));
+//!
+//! c.write_point_ref(&p);
+//! ```
+//!
+//! `Client::write` uses the borrowed form of derived metrics, so string tags and fields are not copied.
+//!
+//! ## Encoding line protocol with [crate::LineEncoder]
+//!
+//! [crate::LineEncoder] appends line protocol straight into any `fmt::Write` or `io::Write`, e.g. to write points
+//! to a file or batch them into your own buffer. Reusing the encoder and buffer avoids allocating per point:
+//!
+//! ```rust
+//! use telegraf::{point, LineEncoder};
+//!
+//! let mut enc = LineEncoder::new();
+//! let mut buf = String::new();
+//! enc.encode_point(&mut buf, &point!("measurement", ("field1", 10))).unwrap();
+//! assert_eq!(buf, "measurement field1=10i\n");
+//! ```
+//!
+//! ## Output formats in [crate::format]
+//!
+//! Besides line protocol, the `Client` can write other formats understood by Telegraf's `socket_listener`,
+//! selected to match its `data_format`: Graphite, StatsD, Telegraf JSON, MessagePack and OpenTSDB. Points and derived metrics are written
+//! the same way in every format, and each serializer can also be used standalone via its `encode_point` methods:
+//!
+//! ```rust,no_run
+//! use telegraf::{format::{Format, Graphite}, Client};
+//!
+//! let mut client = Client::new("tcp://localhost:2003")
+//!     .unwrap()
+//!     .with_format(Format::Graphite(Graphite::new().with_template("host.measurement.field")));
+//! ```
+//!
+//! To serve the same points on a Prometheus `/metrics` page, encode them with [crate::format::Prometheus]. To export them to InfluxDB tooling or spreadsheets, encode them as annotated CSV with [crate::format::AnnotatedCsv].
+//!
+//! Other formats can be written by implementing [crate::format::Serializer] and passing it to `Client::with_serializer`.
+//!
+//! `statsd://` connections write StatsD by default. Fields are sent as gauges unless configured otherwise:
+//!
+//! ```rust,no_run
+//! use telegraf::{format::{Format, Statsd, StatsdKind}, Client};
+//!
+//! let mut client = Client::new("statsd://localhost:8125")
+//!     .unwrap()
+//!     .with_format(Format::Statsd(Statsd::new().with_field_kind("requests", StatsdKind::Counter)));
+//! ```
+//!
+//! ## Writing over HTTP
+//!
+//! `http://` URLs POST every write as one batch, to Telegraf's `http_listener_v2` (at `/telegraf` by default) or an
+//! InfluxDB write endpoint. InfluxDB v1 parameters (`db`, `rp`, `precision`) are taken from the query string, as are v2
+//! ones (`org`, `bucket`, `precision`), with `token` sent as the `Authorization` header. The client writes timestamps in
+//! the given precision. Non-2xx responses are returned as `TelegrafError::HttpError`, including the message of InfluxDB's
+//! JSON errors:
+//!
+//! ```rust,no_run
+//! use telegraf::{point, Client, TelegrafError};
+//!
+//! let mut client = Client::new("http://localhost:8086?org=acme&bucket=metrics&token=my-token").unwrap();
+//!
+//! match client.write_point(&point!("cpu", ("load", 0.5))) {
+//!     Err(TelegrafError::HttpError(e)) if e.is_retryable() => { /* try again later */ }
+//!     r => r.unwrap(),
+//! }
+//! ```
+//!
+//! ## TLS
+//!
+//! With the `tls` cargo feature, `tls://` connections write line protocol over TLS, e.g. to a `socket_listener` with
+//! `tls_cert` and `tls_key` set, and `https://` URLs are written to like `http://` ones. Servers are verified against the
+//! Mozilla root certificates by default. Use `TlsConfig` to trust other CAs, verify a different server name or send a
+//! client certificate to listeners with `tls_allowed_cacerts`:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tls")] {
+//! use telegraf::{Client, TlsConfig};
+//!
+//! let tls = TlsConfig::new()
+//!     .with_ca_file("/etc/telegraf/ca.pem")
+//!     .with_client_cert_files("/etc/app/cert.pem", "/etc/app/key.pem");
+//! let mut client = Client::new_with_tls("tls://telegraf.internal:8094", tls).unwrap();
+//! # }
+//! ```
+//!
+//! ## Reconnecting
+//!
+//! Once a `tcp://`, `tls://` or `unix://` connection breaks, e.g. because Telegraf restarted, writes keep failing. With a
+//! `Reconnect` policy the client reconnects instead, backing off exponentially while Telegraf is unreachable, and can
+//! retry the write that found the connection broken:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use telegraf::{Client, Reconnect};
+//!
+//! let mut client = Client::new("tcp://localhost:8094")
+//!     .unwrap()
+//!     .with_reconnect(Reconnect::new().with_write_retry(true).with_max_backoff(Duration::from_secs(10)));
+//! ```
+//!
+//! ## Buffering
+//!
+//! `BufferedClient` takes points from hot paths and batches them in memory. A background thread writes the batch once it
+//! reaches `max_batch_points` points or about `max_batch_bytes` bytes, or when `flush_interval` elapses, and writes a
+//! backlog in batches of that size. Writes fail once `max_pending_points` points are waiting. Call `flush` to write the
+//! batch right away; dropping the client writes whatever is left:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use telegraf::{point, BatchConfig, BufferedClient, Client};
+//!
+//! let client = Client::new("tcp://localhost:8094").unwrap();
+//! let config = BatchConfig::new()
+//!     .with_max_batch_points(500)
+//!     .with_flush_interval(Duration::from_secs(5))
+//!     .with_error_listener(|e| eprintln!("telegraf: {}", e));
+//! let buffered = BufferedClient::new(client, config);
+//! buffered.write_point(&point!("requests", ("status", 200))).unwrap();
+//! ```
+//!
+//! ## Async
+//!
+//! With the `tokio` cargo feature, `AsyncClient` writes to `tcp://`, `udp://`, `statsd://`, `unix://` and `unixgram://`
+//! URLs without blocking the runtime. It has the same settings as `Client`, and points are validated and encoded the
+//! same way:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> Result<(), telegraf::TelegrafError> {
+//! use telegraf::{point, AsyncClient, Precision};
+//!
+//! let mut client = AsyncClient::new("tcp://localhost:8094")
+//!     .await?
+//!     .with_auto_timestamp(Precision::Milliseconds);
+//! client.write_point(&point!("cpu", ("load", 0.5))).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Parsing line protocol
+//!
+//! Line protocol text can be read back into [crate::Point] values via
+//! [crate::protocol::parse], [crate::protocol::parse_line] or, for streams,
+//! [crate::protocol::parse_reader].
+//!
+//! ```
+//! use telegraf::{point, protocol};
+//!
+//! let points = protocol::parse("# comment\nmeasurement,tag1=a field1=1i 100\n").unwrap();
+//! assert_eq!(points, vec![point!("measurement", ("tag1", "a"), ("field1", 1i64); 100)]);
+//! ```
 
+#[cfg(feature = "tokio")]
+mod async_client;
+mod buffered;
+mod builder;
+mod canonical;
+#[doc(hidden)]
+pub mod derive_support;
+pub mod format;
+mod http;
 pub mod macros;
+mod pipeline;
+mod point_ref;
 pub mod protocol;
+mod reconnect;
+mod series_key;
+#[cfg(feature = "tls")]
+mod tls;
+mod validation;
 
 use std::{
     fmt,
     io::{self, Error, Write},
     net::{Shutdown, SocketAddr, TcpStream, UdpSocket},
+    sync::Arc,
+    time::SystemTime,
 };
 
 #[cfg(target_family = "unix")]
@@ -160,9 +422,26 @@ use std::os::unix::net::{UnixDatagram, UnixStream};
 
 use url::Url;
 
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncClient;
+pub use buffered::{BatchConfig, BufferedClient};
+pub use builder::PointBuilder;
+use format::{Format, Serializer};
+pub use http::HttpError;
+use pipeline::Pipeline;
+pub use point_ref::PointRef;
 use protocol::*;
-pub use protocol::{FieldData, IntoFieldData};
+pub use protocol::{
+    FieldData, FieldDataRef, FieldOrder, IntoFieldData, IntoTimestamp, LineEncoder, Precision,
+    Timestamp, UnsignedMode,
+};
+use reconnect::{Connect, Reconnector};
+pub use reconnect::{Reconnect, ReconnectEvent};
+pub use series_key::SeriesKey;
 pub use telegraf_derive::*;
+#[cfg(feature = "tls")]
+pub use tls::TlsConfig;
+pub use validation::{ValidationError, MAX_STRING_FIELD_LEN};
 
 /// Common result type. Only meaningful response is
 /// an error.
@@ -196,7 +475,33 @@ pub type TelegrafResult = Result<(), TelegrafError>;
 pub trait Metric {
     /// Converts internal attributes
     /// to a Point format.
+    ///
+    /// Timestamps that cannot be converted are left unset,
+    /// so Telegraf stamps the point when it arrives. Use
+    /// [Metric::try_to_point] to report them instead.
     fn to_point(&self) -> Point;
+
+    /// Fallible version of [Metric::to_point]. Reports
+    /// timestamps that cannot be converted, such as times
+    /// before the Unix epoch, which `to_point` leaves unset.
+    fn try_to_point(&self) -> Result<Point, TelegrafError> {
+        Ok(self.to_point())
+    }
+
+    /// Borrowed version of [Metric::try_to_point], used by
+    /// [Client::write]. The derive macro implements this without
+    /// copying string tags and fields.
+    fn to_point_ref(&self) -> Result<PointRef<'_>, TelegrafError> {
+        self.try_to_point().map(PointRef::from)
+    }
+
+    /// Calls `f` with the borrowed point, as used by
+    /// [Client::write]. Unlike [Metric::to_point_ref] the point
+    /// may borrow from the stack, so the derive macro builds it
+    /// without heap allocation for structs with no `Option` members.
+    fn with_point_ref(&self, f: &mut dyn FnMut(&PointRef<'_>) -> TelegrafResult) -> TelegrafResult {
+        f(&self.to_point_ref()?)
+    }
 }
 
 /// Error enum for library failures.
@@ -208,6 +513,14 @@ pub enum TelegrafError {
     ConnectionError(String),
     /// Error when a bad protocol is created.
     BadProtocol(String),
+    /// Error when line protocol input cannot be parsed.
+    ParseError(ParseError),
+    /// Error when a time cannot be represented as a timestamp.
+    BadTimestamp(String),
+    /// Error when a point fails validation.
+    InvalidPoint(Vec<ValidationError>),
+    /// Error response from an HTTP write endpoint.
+    HttpError(HttpError),
 }
 
 /// A single influx metric. Handles conversion from Rust types
@@ -215,10 +528,15 @@ pub enum TelegrafError {
 ///
 /// Telegraf protocol requires at least one field, whereas
 /// tags are completely optional. Attempting to write a point
-/// without any fields will return a [crate::TelegrafError].
+/// without any fields, or one that otherwise fails
+/// [Point::validate], will return a [crate::TelegrafError].
 ///
 /// Creation of points is made easier via the [crate::point] macro.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Points compare and hash equal when they have the same contents,
+/// regardless of the order tags and fields were added in and of the
+/// precision their timestamps were given in.
+#[derive(Debug, Clone)]
 pub struct Point {
     pub measurement: String,
     pub tags: Vec<Tag>,
@@ -230,8 +548,23 @@ pub struct Point {
 /// and writing.
 pub struct Client {
     conn: Connector,
+    pipeline: Pipeline,
+    connect: Connect,
+    reconnect: Option<Reconnector>,
+}
+
+/// Source of the current time for [Client::with_auto_timestamp].
+///
+/// Implemented for closures returning a [SystemTime], which
+/// makes it easy to use a fixed time in tests.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
 }
 
+/// [Clock] reading the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
 /// Different types of connections that the library supports.
 enum Connector {
     Tcp(TcpStream),
@@ -240,6 +573,9 @@ enum Connector {
     Unix(UnixStream),
     #[cfg(target_family = "unix")]
     Unixgram(UnixDatagram),
+    #[cfg(feature = "tls")]
+    Tls(Box<tls::TlsStream>),
+    Http(http::HttpConnector),
 }
 
 impl Point {
@@ -261,7 +597,7 @@ impl Point {
                 value: v.field_data(),
             })
             .collect();
-        let ts = timestamp.map(|t| Timestamp { value: t });
+        let ts = timestamp.map(Timestamp::from);
         Self {
             measurement,
             tags: t,
@@ -269,29 +605,6 @@ impl Point {
             timestamp: ts,
         }
     }
-
-    fn to_lp(&self) -> LineProtocol {
-        let tag_attrs: Vec<Attr> = self.tags.iter().cloned().map(Attr::Tag).collect();
-        let field_attrs: Vec<Attr> = self.fields.iter().cloned().map(Attr::Field).collect();
-        let timestamp_attr: Vec<Attr> = self
-            .timestamp
-            .iter()
-            .cloned()
-            .map(Attr::Timestamp)
-            .collect();
-        let tag_str = if tag_attrs.is_empty() {
-            None
-        } else {
-            Some(format_attr(tag_attrs))
-        };
-        let field_str = format_attr(field_attrs);
-        let timestamp_str = if timestamp_attr.is_empty() {
-            None
-        } else {
-            Some(format_attr(timestamp_attr))
-        };
-        LineProtocol::new(self.measurement.clone(), tag_str, field_str, timestamp_str)
-    }
 }
 
 impl Client {
@@ -299,46 +612,159 @@ impl Client {
     /// provided URL.
     pub fn new(conn_url: &str) -> Result<Self, TelegrafError> {
         let conn = Connector::new(conn_url)?;
-        Ok(Self { conn })
+        let url = conn_url.to_owned();
+        let client = Self::from_connector(conn, Arc::new(move || Connector::new(&url)));
+        match Url::parse(conn_url) {
+            Ok(u) if u.scheme() == "statsd" => {
+                Ok(client.with_format(Format::Statsd(format::Statsd::new())))
+            }
+            _ => Ok(client),
+        }
+    }
+
+    /// Creates a new Client for a `tls://` or `https://` URL,
+    /// connecting with the given TLS settings.
+    #[cfg(feature = "tls")]
+    pub fn new_with_tls(conn_url: &str, tls: TlsConfig) -> Result<Self, TelegrafError> {
+        let u = Url::parse(conn_url).map_err(|_| {
+            TelegrafError::BadProtocol(format!("invalid connection URL {}", conn_url))
+        })?;
+        let tls = tls::TlsConnector::new(&tls, u.host_str().unwrap_or_default())?;
+        let conn = Connector::new_tls(&u, tls.clone())?;
+        Ok(Self::from_connector(
+            conn,
+            Arc::new(move || Connector::new_tls(&u, tls.clone())),
+        ))
+    }
+
+    fn from_connector(conn: Connector, connect: Connect) -> Self {
+        // HTTP endpoints are told the precision in the URL.
+        let mut encoder = LineEncoder::new();
+        if let Connector::Http(c) = &conn {
+            if let Some(p) = c.precision() {
+                encoder = encoder.with_precision(p);
+            }
+        }
+        Self {
+            conn,
+            pipeline: Pipeline::new(encoder),
+            connect,
+            reconnect: None,
+        }
+    }
+
+    /// Sets the precision timestamps are written in. This must
+    /// match the `precision` of the Telegraf listener. Timestamps
+    /// of other precisions are converted when written.
+    ///
+    /// For `http://` and `https://` URLs the `precision` query
+    /// parameter is set to match.
+    ///
+    /// Defaults to [Precision::Nanoseconds], or the `precision`
+    /// of HTTP URLs.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        if let Connector::Http(c) = &mut self.conn {
+            c.set_precision(precision);
+        }
+        self.pipeline.encoder = self.pipeline.encoder.with_precision(precision);
+        self
+    }
+
+    /// Sets how unsigned integer fields are written. Use one of
+    /// the signed modes when writing to InfluxDB 1.x, or to
+    /// Telegraf without `influx_uint_support`.
+    ///
+    /// Defaults to [UnsignedMode::Native].
+    pub fn with_unsigned_mode(mut self, mode: UnsignedMode) -> Self {
+        self.pipeline.encoder = self.pipeline.encoder.with_unsigned_mode(mode);
+        self
+    }
+
+    /// Sets the format points are written in. This must match
+    /// the `data_format` of the Telegraf listener.
+    ///
+    /// Defaults to [Format::LineProtocol]. The precision, unsigned
+    /// and field order settings only apply to line protocol.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.pipeline.serializer = match format {
+            Format::LineProtocol => None,
+            format => Some(Box::new(format)),
+        };
+        self
+    }
+
+    /// Sets a custom [Serializer] points are written with,
+    /// replacing the configured [Format].
+    pub fn with_serializer<S: Serializer + Send + 'static>(mut self, serializer: S) -> Self {
+        self.pipeline.serializer = Some(Box::new(serializer));
+        self
+    }
+
+    /// Sets the order fields are written in. Tags are always
+    /// sorted by key, as recommended by InfluxDB.
+    ///
+    /// Defaults to [FieldOrder::Preserve].
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.pipeline.encoder = self.pipeline.encoder.with_field_order(order);
+        self
+    }
+
+    /// Stamps points that have no timestamp with the current
+    /// time, in the given precision, when they are written.
+    /// Otherwise the Telegraf daemon stamps them on arrival.
+    ///
+    /// All points of a [Client::write_points] batch get the
+    /// same timestamp.
+    pub fn with_auto_timestamp(mut self, precision: Precision) -> Self {
+        self.pipeline.auto_timestamp = Some(precision);
+        self
+    }
+
+    /// Sets the clock used for automatic timestamps.
+    ///
+    /// Defaults to [SystemClock].
+    pub fn with_clock<C: Clock + Send + 'static>(mut self, clock: C) -> Self {
+        self.pipeline.clock = Box::new(clock);
+        self
+    }
+
+    /// Reconnects `tcp://`, `tls://` and `unix://` connections that break,
+    /// e.g. when Telegraf restarts, following the given [Reconnect]
+    /// policy. Other connections are unaffected.
+    ///
+    /// Without this, writes keep failing once the connection broke.
+    pub fn with_reconnect(mut self, policy: Reconnect) -> Self {
+        self.reconnect = Some(Reconnector::new(self.connect.clone(), policy));
+        self
     }
 
     /// Writes the protocol representation of a point
     /// to the established connection.
     pub fn write_point(&mut self, pt: &Point) -> TelegrafResult {
-        if pt.fields.is_empty() {
-            return Err(TelegrafError::BadProtocol(
-                "points must have at least 1 field".to_owned(),
-            ));
-        }
-
-        let lp = pt.to_lp();
-        let bytes = lp.to_str().as_bytes();
-        self.write_to_conn(bytes)
+        self.pipeline.encode_point(pt)?;
+        self.write_buf()
     }
 
     /// Joins multiple points together and writes them in a batch. Useful
     /// if you want to write lots of points but not overwhelm local service or
     /// you want to ensure all points have the exact same timestamp.
     pub fn write_points(&mut self, pts: &[Point]) -> TelegrafResult {
-        if pts.iter().any(|p| p.fields.is_empty()) {
-            return Err(TelegrafError::BadProtocol(
-                "points must have at least 1 field".to_owned(),
-            ));
-        }
+        self.pipeline.encode_points(pts)?;
+        self.write_buf()
+    }
 
-        let lp = pts
-            .iter()
-            .map(|p| p.to_lp().to_str().to_owned())
-            .collect::<Vec<String>>()
-            .join("");
-        self.write_to_conn(lp.as_bytes())
+    /// Writes a borrowed point to the established connection,
+    /// without converting it to an owned [Point].
+    pub fn write_point_ref(&mut self, pt: &PointRef) -> TelegrafResult {
+        self.pipeline.encode_point_ref(pt)?;
+        self.write_buf()
     }
 
     /// Convenience wrapper around writing points for types
     /// that implement [crate::Metric].
     pub fn write<M: Metric>(&mut self, metric: &M) -> TelegrafResult {
-        let pt = metric.to_point();
-        self.write_point(&pt)
+        metric.with_point_ref(&mut |pt| self.pipeline.encode_point_ref(pt))?;
+        self.write_buf()
     }
 
     /// Closes and cleans up socket connection.
@@ -348,7 +774,30 @@ impl Client {
 
     /// Writes byte array to internal outgoing socket.
     pub fn write_to_conn(&mut self, data: &[u8]) -> TelegrafResult {
-        self.conn.write(data).map(|_| Ok(()))?
+        match &mut self.reconnect {
+            Some(r) => r.write(&mut self.conn, data),
+            None => self.conn.write(data),
+        }
+    }
+
+    /// Writes the encoded contents of the internal buffer.
+    fn write_buf(&mut self) -> TelegrafResult {
+        match &mut self.reconnect {
+            Some(r) => r.write(&mut self.conn, &self.pipeline.buf),
+            None => self.conn.write(&self.pipeline.buf),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F: Fn() -> SystemTime> Clock for F {
+    fn now(&self) -> SystemTime {
+        self()
     }
 }
 
@@ -363,19 +812,46 @@ impl Connector {
             Unixgram(c) => c.shutdown(Shutdown::Both),
             // Udp socket doesnt have a graceful close.
             Udp(_) => Ok(()),
+            #[cfg(feature = "tls")]
+            Tls(c) => c.close(),
+            Http(c) => c.close(),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> TelegrafResult {
+        match self {
+            Self::Http(c) => c.write(buf),
+            _ => Ok(self.write_socket(buf)?),
         }
     }
 
-    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
-        let r = match self {
-            Self::Tcp(c) => c.write(buf),
-            Self::Udp(c) => c.send(buf),
+    /// Writes to a socket, keeping the I/O error so broken
+    /// connections can be detected.
+    fn write_socket(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Tcp(c) => c.write_all(buf),
+            Self::Udp(c) => c.send(buf).map(|_| ()),
             #[cfg(target_family = "unix")]
-            Self::Unix(c) => c.write(buf),
+            Self::Unix(c) => c.write_all(buf),
             #[cfg(target_family = "unix")]
-            Self::Unixgram(c) => c.send(buf),
-        };
-        r.map(|_| Ok(()))?
+            Self::Unixgram(c) => c.send(buf).map(|_| ()),
+            #[cfg(feature = "tls")]
+            Self::Tls(c) => c.write_all(buf).and_then(|_| c.flush()),
+            Self::Http(_) => unreachable!("HTTP connections are written with Connector::write"),
+        }
+    }
+
+    /// Whether this is a `tcp://`, `tls://` or `unix://` stream,
+    /// which can be reconnected.
+    fn is_stream(&self) -> bool {
+        match self {
+            Self::Tcp(_) => true,
+            #[cfg(feature = "tls")]
+            Self::Tls(_) => true,
+            #[cfg(target_family = "unix")]
+            Self::Unix(_) => true,
+            _ => false,
+        }
     }
 
     fn new(url: &str) -> Result<Self, TelegrafError> {
@@ -388,7 +864,7 @@ impl Connector {
                         let conn = TcpStream::connect(&*addr)?;
                         Ok(Connector::Tcp(conn))
                     }
-                    "udp" => {
+                    "udp" | "statsd" => {
                         let addr = u.socket_addrs(|| None)?;
                         let conn = UdpSocket::bind(&[SocketAddr::from(([0, 0, 0, 0], 0))][..])?;
                         conn.connect(&*addr)?;
@@ -409,6 +885,17 @@ impl Connector {
                         conn.set_nonblocking(true)?;
                         Ok(Connector::Unixgram(conn))
                     }
+                    "http" => Ok(Connector::Http(http::HttpConnector::new(&u)?)),
+                    #[cfg(feature = "tls")]
+                    "tls" | "https" => {
+                        let host = u.host_str().unwrap_or_default();
+                        Self::new_tls(&u, tls::TlsConnector::new(&TlsConfig::new(), host)?)
+                    }
+                    #[cfg(not(feature = "tls"))]
+                    "tls" | "https" => Err(TelegrafError::BadProtocol(format!(
+                        "{} connections require the tls feature",
+                        scheme
+                    ))),
                     _ => Err(TelegrafError::BadProtocol(format!(
                         "unknown connection protocol {}",
                         scheme
@@ -421,6 +908,22 @@ impl Connector {
             ))),
         }
     }
+
+    #[cfg(feature = "tls")]
+    fn new_tls(u: &Url, tls: tls::TlsConnector) -> Result<Self, TelegrafError> {
+        match u.scheme() {
+            "tls" => {
+                let addr = u.socket_addrs(|| None)?;
+                let conn = tls.connect(TcpStream::connect(&*addr)?)?;
+                Ok(Connector::Tls(Box::new(conn)))
+            }
+            "https" => Ok(Connector::Http(http::HttpConnector::new(u)?.with_tls(tls))),
+            scheme => Err(TelegrafError::BadProtocol(format!(
+                "TLS is not supported for {} connections",
+                scheme
+            ))),
+        }
+    }
 }
 
 impl fmt::Display for TelegrafError {
@@ -429,29 +932,40 @@ impl fmt::Display for TelegrafError {
             TelegrafError::IoError(ref e) => write!(f, "{}", e),
             TelegrafError::ConnectionError(ref e) => write!(f, "{}", e),
             TelegrafError::BadProtocol(ref e) => write!(f, "{}", e),
+            TelegrafError::ParseError(ref e) => write!(f, "{}", e),
+            TelegrafError::BadTimestamp(ref e) => write!(f, "{}", e),
+            TelegrafError::InvalidPoint(ref errs) => {
+                let msgs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+                write!(f, "invalid point: {}", msgs.join("; "))
+            }
+            TelegrafError::HttpError(ref e) => write!(f, "{}", e),
         }
     }
 }
 
-impl From<Error> for TelegrafError {
-    fn from(e: Error) -> Self {
-        Self::ConnectionError(e.to_string())
+impl From<ParseError> for TelegrafError {
+    fn from(e: ParseError) -> Self {
+        Self::ParseError(e)
     }
 }
 
-trait TelegrafUnwrap<T> {
-    fn t_unwrap(self, msg: &str) -> Result<T, TelegrafError>;
-}
-
-impl<T> TelegrafUnwrap<T> for Option<T> {
-    fn t_unwrap(self, msg: &str) -> Result<T, TelegrafError> {
-        self.ok_or_else(|| TelegrafError::ConnectionError(msg.to_owned()))
+impl From<Error> for TelegrafError {
+    fn from(e: Error) -> Self {
+        Self::ConnectionError(e.to_string())
     }
 }
 
+/// Shows the point as line protocol. Newlines in names and tag
+/// values, which [LineEncoder] rejects, are shown as `\n`.
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_lp().to_str())
+        protocol::display_line(
+            f,
+            &self.measurement,
+            &self.tags,
+            &self.fields,
+            self.timestamp,
+        )
     }
 }
 
@@ -459,6 +973,18 @@ impl fmt::Display for Point {
 mod tests {
     use super::*;
 
+    fn lp_in(
+        p: &Point,
+        precision: Precision,
+        unsigned: UnsignedMode,
+    ) -> Result<String, TelegrafError> {
+        let mut enc = LineEncoder::new()
+            .with_precision(precision)
+            .with_unsigned_mode(unsigned);
+        let mut s = String::new();
+        enc.encode_point(&mut s, p).map(|_| s)
+    }
+
     #[test]
     fn can_create_point_lp_ts_no_tags() {
         let p = Point::new(
@@ -471,8 +997,7 @@ mod tests {
             Some(10),
         );
 
-        let lp = p.to_lp();
-        assert_eq!(lp.to_str(), "Foo f1=10i,f2=10.3 10\n");
+        assert_eq!(p.to_string(), "Foo f1=10i,f2=10.3 10\n");
     }
 
     #[test]
@@ -488,8 +1013,7 @@ mod tests {
             Some(10),
         );
 
-        let lp = p.to_lp();
-        assert_eq!(lp.to_str(), "Foo,t1=v f1=10i,f2=10.3,f3=\"b\" 10\n");
+        assert_eq!(p.to_string(), "Foo,t1=v f1=10i,f2=10.3,f3=\"b\" 10\n");
     }
 
     #[test]
@@ -505,8 +1029,7 @@ mod tests {
             None,
         );
 
-        let lp = p.to_lp();
-        assert_eq!(lp.to_str(), "Foo,t1=v f1=10i,f2=10.3,f3=\"b\"\n");
+        assert_eq!(p.to_string(), "Foo,t1=v f1=10i,f2=10.3,f3=\"b\"\n");
     }
 
     #[test]
@@ -521,7 +1044,170 @@ mod tests {
             None,
         );
 
-        let lp = p.to_lp();
-        assert_eq!(lp.to_str(), "Foo f1=10i,f2=10.3\n");
+        assert_eq!(p.to_string(), "Foo f1=10i,f2=10.3\n");
+    }
+
+    #[test]
+    fn can_create_point_lp_escaped() {
+        let p = Point::new(
+            String::from("Foo bar"),
+            vec![("t 1".to_owned(), "a,b=c".to_owned())],
+            vec![("f=1".to_owned(), Box::new("say \"hi\""))],
+            None,
+        );
+
+        assert_eq!(
+            p.to_string(),
+            "Foo\\ bar,t\\ 1=a\\,b\\=c f\\=1=\"say \\\"hi\\\"\"\n"
+        );
+    }
+
+    #[test]
+    fn can_create_point_lp_in_precision() {
+        let mut p = Point::new(
+            String::from("Foo"),
+            vec![],
+            vec![("f1".to_owned(), Box::new(10))],
+            None,
+        );
+        p.timestamp = Some(Timestamp::new(1_500, Precision::Milliseconds));
+
+        assert_eq!(p.to_string(), "Foo f1=10i 1500\n");
+        assert_eq!(
+            lp_in(&p, Precision::Nanoseconds, UnsignedMode::Native).unwrap(),
+            "Foo f1=10i 1500000000\n"
+        );
+        assert_eq!(
+            lp_in(&p, Precision::Seconds, UnsignedMode::Native).unwrap(),
+            "Foo f1=10i 1\n"
+        );
+    }
+
+    #[test]
+    fn can_auto_timestamp_points() {
+        use std::{io::Read, net::TcpListener, time::Duration};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let now = SystemTime::UNIX_EPOCH + Duration::from_millis(1_500);
+        let mut c = Client::new(&url)
+            .unwrap()
+            .with_auto_timestamp(Precision::Milliseconds)
+            .with_clock(move || now);
+        let (mut conn, _) = listener.accept().unwrap();
+
+        c.write_point(&point!("Foo", ("f1", 1))).unwrap();
+        c.write_points(&[point!("Foo", ("f1", 2)), point!("Foo", ("f1", 3); 10)])
+            .unwrap();
+        c.close().unwrap();
+
+        let mut out = String::new();
+        conn.read_to_string(&mut out).unwrap();
+        assert_eq!(
+            out,
+            "Foo f1=1i 1500000000\nFoo f1=2i 1500000000\nFoo f1=3i 10\n"
+        );
+    }
+
+    #[test]
+    fn can_write_graphite() {
+        use std::{io::Read, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let mut c = Client::new(&url)
+            .unwrap()
+            .with_format(Format::Graphite(format::Graphite::new()))
+            .with_auto_timestamp(Precision::Seconds)
+            .with_clock(|| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(7));
+        let (mut conn, _) = listener.accept().unwrap();
+
+        c.write_point(&point!("cpu", ("host", "a"), ("load", 1)))
+            .unwrap();
+        c.close().unwrap();
+
+        let mut out = String::new();
+        conn.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "a.cpu.load 1 7\n");
+    }
+
+    #[test]
+    fn can_write_with_custom_serializer() {
+        use std::{io::Read, net::TcpListener};
+
+        struct Count;
+
+        impl Serializer for Count {
+            fn serialize(&mut self, buf: &mut Vec<u8>, _: &PointRef) -> TelegrafResult {
+                self.serialize_batch(buf, &[])
+            }
+
+            fn serialize_batch(&mut self, buf: &mut Vec<u8>, pts: &[PointRef]) -> TelegrafResult {
+                buf.extend_from_slice(format!("{}\n", pts.len()).as_bytes());
+                Ok(())
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let mut c = Client::new(&url).unwrap().with_serializer(Count);
+        let (mut conn, _) = listener.accept().unwrap();
+
+        c.write_points(&[point!("a", ("f", 1)), point!("b", ("f", 2))])
+            .unwrap();
+        c.close().unwrap();
+
+        let mut out = String::new();
+        conn.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "2\n");
+    }
+
+    #[test]
+    fn can_write_statsd() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!("statsd://{}", server.local_addr().unwrap());
+        let mut c = Client::new(&url).unwrap();
+
+        c.write_point(&point!("m", ("host", "a"), ("f", 1)))
+            .unwrap();
+
+        let mut buf = [0; 64];
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"m.f,host=a:1|g\n");
+    }
+
+    #[test]
+    fn can_create_point_lp_with_signed_unsigned() {
+        let p = Point::new(
+            String::from("Foo"),
+            vec![],
+            vec![("f1".to_owned(), Box::new(10u64))],
+            None,
+        );
+        let lp = lp_in(&p, Precision::Nanoseconds, UnsignedMode::Signed);
+        assert_eq!(lp.unwrap(), "Foo f1=10i\n");
+
+        let p = Point::new(
+            String::from("Foo"),
+            vec![],
+            vec![("f1".to_owned(), Box::new(u64::MAX))],
+            None,
+        );
+        assert!(lp_in(&p, Precision::Nanoseconds, UnsignedMode::Signed).is_err());
+        let lp = lp_in(&p, Precision::Nanoseconds, UnsignedMode::SignedSaturating);
+        assert_eq!(lp.unwrap(), format!("Foo f1={}i\n", i64::MAX));
+    }
+
+    #[test]
+    fn cannot_create_point_lp_with_newline() {
+        let p = Point::new(
+            String::from("Foo"),
+            vec![("t1".to_owned(), "a\nb".to_owned())],
+            vec![("f1".to_owned(), Box::new(10))],
+            None,
+        );
+
+        assert!(lp_in(&p, Precision::Nanoseconds, UnsignedMode::Native).is_err());
+        assert_eq!(p.to_string(), "Foo,t1=a\\nb f1=10i\n");
     }
 }
@@ -39,6 +39,21 @@
 ///
 /// let p = point!("measure", ("f1", "f1v"); 100);
 /// ```
+///
+/// The timestamp can be anything implementing [crate::IntoTimestamp].
+/// Times that cannot be converted, such as ones before the Unix
+/// epoch, leave the timestamp unset, so Telegraf stamps the point
+/// when it arrives. Use [crate::PointBuilder::timestamp] to have
+/// them reported as an error instead.
+///
+/// Timestamps given as integers are in nanoseconds. Other
+/// precisions can be declared with a [crate::Timestamp]:
+///
+/// ```
+/// use telegraf::{point, Precision, Timestamp};
+///
+/// let p = point!("measure", ("f1", "f1v"); Timestamp::new(100, Precision::Milliseconds));
+/// ```
 #[macro_export]
 macro_rules! point {
     ($measure:expr, $(($fname:expr, $fval:expr)) + $(; $ts:expr)?) => {
@@ -48,17 +63,19 @@ macro_rules! point {
             ];
 
             #[allow(unused_mut)]
-            let mut timestamp: Option<u64> = None;
-            $(
-                timestamp = timestamp.or(Some($ts));
-            )?
-
-            $crate::Point::new(
+            let mut point = $crate::Point::new(
                 String::from($measure),
                 Vec::new(),
                 fields,
-                timestamp,
-            )
+                None,
+            );
+            $(
+                point.timestamp = $crate::IntoTimestamp::to_timestamp(
+                    &$ts,
+                    $crate::Precision::Nanoseconds,
+                ).ok();
+            )?
+            point
         }
     };
     ($measure:expr, $(($tname:expr, $tval:expr)) +, $(($fname:expr, $fval:expr)) + $(; $ts:expr)?) => {
@@ -74,17 +91,83 @@ macro_rules! point {
             )*
 
             #[allow(unused_mut)]
-            let mut timestamp: Option<u64> = None;
-            $(
-                timestamp = timestamp.or(Some($ts));
-            )?
-
-            $crate::Point::new(
+            let mut point = $crate::Point::new(
                 String::from($measure),
                 tags,
                 fields,
-                timestamp,
-            )
+                None,
+            );
+            $(
+                point.timestamp = $crate::IntoTimestamp::to_timestamp(
+                    &$ts,
+                    $crate::Precision::Nanoseconds,
+                ).ok();
+            )?
+            point
+        }
+    };
+}
+
+/// Shorthand macro for generating [crate::PointRef] structs
+/// without heap allocation.
+///
+/// Syntax is the same as the [crate::point] macro. Tag values
+/// must be `&str`, `String` or `Cow<str>` and field values must be
+/// convertible into a [crate::FieldDataRef].
+///
+/// The tag and field lists are borrowed from temporaries, so the
+/// point should be bound with `let` or used in the same statement.
+/// Timestamps that cannot be converted are left unset, as with
+/// the [crate::point] macro.
+///
+/// # Examples
+///
+/// ```no_run
+/// use telegraf::{point_ref, Client};
+///
+/// let mut client = Client::new("tcp://localhost:8094").unwrap();
+///
+/// let host = String::from("web01");
+/// let p = point_ref!("cpu", ("host", host.as_str()), ("load", 0.5) ("procs", 12u64));
+/// client.write_point_ref(&p).unwrap();
+/// ```
+#[macro_export]
+macro_rules! point_ref {
+    (@ts) => {
+        None
+    };
+    (@ts $ts:expr) => {
+        $crate::IntoTimestamp::to_timestamp(&$ts, $crate::Precision::Nanoseconds).ok()
+    };
+    ($measure:expr, $(($fname:expr, $fval:expr)) + $(; $ts:expr)?) => {
+        $crate::PointRef {
+            measurement: ::std::borrow::Cow::from($measure),
+            tags: ::std::borrow::Cow::Borrowed(&[]),
+            fields: ::std::borrow::Cow::Borrowed(&[
+                $($crate::protocol::FieldRef {
+                    name: ::std::borrow::Cow::from($fname),
+                    value: $crate::FieldDataRef::from($fval),
+                }),*
+            ]),
+            timestamp: $crate::point_ref!(@ts $($ts)?),
+        }
+    };
+    ($measure:expr, $(($tname:expr, $tval:expr)) +, $(($fname:expr, $fval:expr)) + $(; $ts:expr)?) => {
+        $crate::PointRef {
+            measurement: ::std::borrow::Cow::from($measure),
+            tags: ::std::borrow::Cow::Borrowed(&[
+                $($crate::protocol::TagRef {
+                    name: ::std::borrow::Cow::from($tname),
+                    value: ::std::borrow::Cow::from($tval),
+                }),*
+            ]),
+            fields: ::std::borrow::Cow::Borrowed(&[
+                $($crate::protocol::FieldRef {
+                    name: ::std::borrow::Cow::from($fname),
+                    value: $crate::FieldDataRef::from($fval),
+                }),*
+            ]),
+            timestamp: $crate::point_ref!(@ts $($ts)?),
         }
     };
 }
@@ -108,6 +191,12 @@ mod tests {
         assert_eq!(p, exp);
     }
 
+    #[test]
+    fn can_create_with_ts_precision() {
+        let p = point!("test", ("f", "v"); Timestamp::new(10, Precision::Seconds));
+        assert_eq!(p.timestamp, Some(Timestamp::new(10, Precision::Seconds)));
+    }
+
     #[test]
     fn can_create_with_tag() {
         let p = point!("test", ("t", "v")("t2", "v2"), ("f", "v"));
@@ -134,4 +223,15 @@ mod tests {
         );
         assert_eq!(p, exp);
     }
+
+    #[test]
+    fn can_create_point_ref() {
+        let v = String::from("v");
+        let p = point_ref!("test", ("t", v.as_str())("t2", "v2"), ("f", "v")("i", 1); 10);
+        let exp = point!("test", ("t", "v")("t2", "v2"), ("f", "v")("i", 1); 10);
+        assert_eq!(p.to_point(), exp);
+
+        let p = point_ref!("test", ("f", 1.5));
+        assert_eq!(p.to_point(), point!("test", ("f", 1.5)));
+    }
 }
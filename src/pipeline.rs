@@ -0,0 +1,104 @@
+use crate::{
+    format::{Bytes, Serializer},
+    protocol::LineEncoder,
+    Clock, IntoTimestamp, Point, PointRef, Precision, SystemClock, TelegrafError, TelegrafResult,
+    Timestamp,
+};
+
+/// Validation, timestamping and serialization of points, shared
+/// by [crate::Client] and the async client. Encoded points are
+/// left in `buf` for the client to write to its connection.
+pub(crate) struct Pipeline {
+    pub(crate) encoder: LineEncoder,
+    /// Writes line protocol with `encoder` when unset.
+    pub(crate) serializer: Option<Box<dyn Serializer + Send>>,
+    pub(crate) auto_timestamp: Option<Precision>,
+    pub(crate) clock: Box<dyn Clock + Send>,
+    /// Reused across writes to avoid allocating for every batch.
+    pub(crate) buf: Vec<u8>,
+}
+
+impl Pipeline {
+    pub(crate) fn new(encoder: LineEncoder) -> Self {
+        Self {
+            encoder,
+            serializer: None,
+            auto_timestamp: None,
+            clock: Box::new(SystemClock),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Turns off automatic timestamping, returning its precision
+    /// and clock for a caller that stamps points itself.
+    pub(crate) fn take_auto_timestamp(&mut self) -> Option<(Precision, Box<dyn Clock + Send>)> {
+        let clock = std::mem::replace(&mut self.clock, Box::new(SystemClock));
+        self.auto_timestamp.take().map(|p| (p, clock))
+    }
+
+    /// Validates and encodes a point.
+    pub(crate) fn encode_point(&mut self, pt: &Point) -> TelegrafResult {
+        self.encode_points(std::slice::from_ref(pt))
+    }
+
+    /// Validates and encodes a batch of points, which all get
+    /// the same automatic timestamp. Line protocol is encoded
+    /// straight from the owned points.
+    pub(crate) fn encode_points(&mut self, pts: &[Point]) -> TelegrafResult {
+        for p in pts {
+            p.validate().map_err(TelegrafError::InvalidPoint)?;
+        }
+
+        let now = self.now()?;
+        if self.serializer.is_none() {
+            self.buf.clear();
+            let (encoder, mut w) = (&mut self.encoder, Bytes(&mut self.buf));
+            return pts
+                .iter()
+                .try_for_each(|p| encoder.encode_point_at(&mut w, p, p.timestamp.or(now)));
+        }
+
+        let pts: Vec<PointRef> = pts
+            .iter()
+            .map(|p| {
+                let mut p = PointRef::from(p);
+                p.timestamp = p.timestamp.or(now);
+                p
+            })
+            .collect();
+        self.encode(&pts)
+    }
+
+    /// Validates and encodes a borrowed point, only copying
+    /// it when it needs a timestamp.
+    pub(crate) fn encode_point_ref(&mut self, pt: &PointRef) -> TelegrafResult {
+        pt.validate().map_err(TelegrafError::InvalidPoint)?;
+
+        match self.now()? {
+            Some(now) if pt.timestamp.is_none() => {
+                let mut pt = pt.clone();
+                pt.timestamp = Some(now);
+                self.encode(std::slice::from_ref(&pt))
+            }
+            _ => self.encode(std::slice::from_ref(pt)),
+        }
+    }
+
+    /// Replaces the buffer with the points
+    /// in the configured format.
+    fn encode(&mut self, pts: &[PointRef]) -> TelegrafResult {
+        self.buf.clear();
+        match &mut self.serializer {
+            Some(s) => s.serialize_batch(&mut self.buf, pts),
+            None => self.encoder.serialize_batch(&mut self.buf, pts),
+        }
+    }
+
+    /// Timestamp for points written without one, if
+    /// automatic timestamping is enabled.
+    fn now(&self) -> Result<Option<Timestamp>, TelegrafError> {
+        self.auto_timestamp
+            .map(|p| self.clock.now().to_timestamp(p))
+            .transpose()
+    }
+}
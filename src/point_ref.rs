@@ -0,0 +1,194 @@
+use std::{borrow::Cow, fmt};
+
+use crate::{
+    protocol::{display_line, Field, FieldRef, Tag, TagRef},
+    validation::validate_parts,
+    FieldData, Point, Timestamp, ValidationError,
+};
+
+/// Borrowed form of [crate::Point].
+///
+/// Every part is a [Cow], so a point can be assembled from
+/// borrowed strings and static slices without any heap
+/// allocation, and written by [crate::Client::write_point_ref]
+/// without being converted to a [crate::Point] first.
+///
+/// Creation of borrowed points is made easier via the
+/// [crate::point_ref] macro.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{protocol::{FieldRef, TagRef}, PointRef};
+///
+/// let host = String::from("web01");
+/// let tags = [TagRef::new("host", host.as_str())];
+/// let fields = [FieldRef::new("load", 0.5)];
+/// let p = PointRef::new("cpu", &tags[..], &fields[..], None);
+/// assert_eq!(p.to_string(), "cpu,host=web01 load=0.5\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct PointRef<'a> {
+    pub measurement: Cow<'a, str>,
+    pub tags: Cow<'a, [TagRef<'a>]>,
+    pub fields: Cow<'a, [FieldRef<'a>]>,
+    pub timestamp: Option<Timestamp>,
+}
+
+impl<'a> PointRef<'a> {
+    /// Creates a new borrowed point that can be written using
+    /// a [crate::Client].
+    pub fn new(
+        measurement: impl Into<Cow<'a, str>>,
+        tags: impl Into<Cow<'a, [TagRef<'a>]>>,
+        fields: impl Into<Cow<'a, [FieldRef<'a>]>>,
+        timestamp: Option<Timestamp>,
+    ) -> Self {
+        Self {
+            measurement: measurement.into(),
+            tags: tags.into(),
+            fields: fields.into(),
+            timestamp,
+        }
+    }
+
+    /// Checks the point against the rules of the Influx data
+    /// model, as per [crate::Point::validate].
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        validate_parts(&self.measurement, &self.tags, &self.fields)
+    }
+
+    /// Copies the point into its owned form.
+    pub fn to_point(&self) -> Point {
+        self.clone().into()
+    }
+}
+
+impl<'a> From<&'a Point> for PointRef<'a> {
+    fn from(p: &'a Point) -> Self {
+        Self {
+            measurement: Cow::Borrowed(&p.measurement),
+            tags: p.tags.iter().map(TagRef::from).collect(),
+            fields: p.fields.iter().map(FieldRef::from).collect(),
+            timestamp: p.timestamp,
+        }
+    }
+}
+
+impl From<Point> for PointRef<'_> {
+    fn from(p: Point) -> Self {
+        Self {
+            measurement: Cow::Owned(p.measurement),
+            tags: p.tags.into_iter().map(TagRef::from).collect(),
+            fields: p.fields.into_iter().map(FieldRef::from).collect(),
+            timestamp: p.timestamp,
+        }
+    }
+}
+
+impl From<PointRef<'_>> for Point {
+    fn from(p: PointRef<'_>) -> Self {
+        Self {
+            measurement: p.measurement.into_owned(),
+            tags: p.tags.iter().map(|t| Tag::from(t.clone())).collect(),
+            fields: p.fields.iter().map(|f| Field::from(f.clone())).collect(),
+            timestamp: p.timestamp,
+        }
+    }
+}
+
+impl<'a> From<&'a Tag> for TagRef<'a> {
+    fn from(t: &'a Tag) -> Self {
+        Self::new(t.name.as_str(), t.value.as_str())
+    }
+}
+
+impl From<Tag> for TagRef<'_> {
+    fn from(t: Tag) -> Self {
+        Self::new(t.name, t.value)
+    }
+}
+
+impl From<TagRef<'_>> for Tag {
+    fn from(t: TagRef<'_>) -> Self {
+        Self {
+            name: t.name.into_owned(),
+            value: t.value.into_owned(),
+        }
+    }
+}
+
+impl<'a> From<&'a Field> for FieldRef<'a> {
+    fn from(f: &'a Field) -> Self {
+        Self::new(f.name.as_str(), &f.value)
+    }
+}
+
+impl From<Field> for FieldRef<'_> {
+    fn from(f: Field) -> Self {
+        Self::new(f.name, f.value)
+    }
+}
+
+impl From<FieldRef<'_>> for Field {
+    fn from(f: FieldRef<'_>) -> Self {
+        Self {
+            name: f.name.into_owned(),
+            value: FieldData::from(f.value),
+        }
+    }
+}
+
+/// Shows the point as per the [fmt::Display] of [Point].
+impl fmt::Display for PointRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_line(
+            f,
+            &self.measurement,
+            &self.tags,
+            &self.fields,
+            self.timestamp,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref, LineEncoder, TelegrafError};
+
+    #[test]
+    fn can_write_borrowed_point() {
+        let host = String::from("a b");
+        let tags = [TagRef::new("host", host.as_str())];
+        let fields = [FieldRef::new("s", "x\"y"), FieldRef::new("n", 1u64)];
+        let p = PointRef::new("m", &tags[..], &fields[..], Some(Timestamp::from(10)));
+        assert_eq!(p.to_string(), "m,host=a\\ b s=\"x\\\"y\",n=1u 10\n");
+    }
+
+    #[test]
+    fn matches_owned_point() {
+        let p = point!("m,1", ("t 2", "v")("a", "b"), ("f=1", "s")("e", -1)("b", true); 5);
+        let r = PointRef::from(&p);
+        assert_eq!(r.to_string(), p.to_string());
+        assert_eq!(r.to_point(), p);
+        assert_eq!(PointRef::from(p.clone()), r);
+    }
+
+    #[test]
+    fn cannot_encode_newlines() {
+        let p = point_ref!("m", ("t", "a\nb"), ("f", 1));
+        let res = LineEncoder::new().encode_point_ref(&mut String::new(), &p);
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+        assert_eq!(p.to_string(), "m,t=a\\nb f=1i\n");
+    }
+
+    #[test]
+    fn can_validate_borrowed_point() {
+        let p = point_ref!("m", ("t", "v"), ("t", 1));
+        assert_eq!(
+            p.validate(),
+            Err(vec![ValidationError::TagFieldCollision("t".to_owned())])
+        );
+    }
+}
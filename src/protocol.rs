@@ -1,3 +1,19 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt, iter,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{format::write_with, TelegrafError};
+
+mod encoder;
+mod parser;
+
+pub use encoder::LineEncoder;
+pub use parser::{parse, parse_line, parse_reader, ParseError, PointReader};
+
 /// Container struct for telegraf line protocol.
 #[derive(Debug)]
 pub struct LineProtocol(String);
@@ -6,6 +22,22 @@ pub struct LineProtocol(String);
 /// implemented by any type that will be used as a Field in a [crate::Point].
 pub trait IntoFieldData {
     fn field_data(&self) -> FieldData;
+
+    /// Borrowing version of [IntoFieldData::field_data], used when
+    /// building a [crate::PointRef]. Types holding strings should
+    /// override this to avoid copying them.
+    fn field_data_ref(&self) -> FieldDataRef<'_> {
+        self.field_data().into()
+    }
+}
+
+/// Used to convert Rust types to a [Timestamp]. Must be implemented
+/// by any type used as the timestamp of a [crate::Point].
+///
+/// `precision` is the unit the timestamp should be expressed in.
+/// Plain integers carry no unit and are taken to already be in it.
+pub trait IntoTimestamp {
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError>;
 }
 
 /// Influx types that can be used in a field.
@@ -18,6 +50,45 @@ pub enum FieldData {
     Str(String),
 }
 
+/// Borrowed form of [FieldData], used by [crate::PointRef].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldDataRef<'a> {
+    Boolean(bool),
+    UNumber(u64),
+    Number(i64),
+    Float(f64),
+    Str(Cow<'a, str>),
+}
+
+/// How [FieldData::UNumber] values are written.
+///
+/// Unsigned integers (`u` suffix) are only understood by InfluxDB 2.x
+/// and Telegraf outputs with `influx_uint_support` enabled. The signed
+/// modes write them as `i` integers instead, for InfluxDB 1.x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnsignedMode {
+    /// Write unsigned integers with a `u` suffix.
+    #[default]
+    Native,
+    /// Write unsigned integers as signed integers. Values
+    /// above `i64::MAX` are an error.
+    Signed,
+    /// Write unsigned integers as signed integers. Values
+    /// above `i64::MAX` are written as `i64::MAX`.
+    SignedSaturating,
+}
+
+/// Order fields are written in. Tags are always
+/// sorted by key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldOrder {
+    /// Write fields in the order they were added to the point.
+    #[default]
+    Preserve,
+    /// Sort fields by key byte order.
+    ByKey,
+}
+
 /// Different types of data collections that can
 /// be in Influx.
 #[derive(Debug)]
@@ -41,20 +112,76 @@ pub struct Field {
     pub value: FieldData,
 }
 
-/// Container struct for timestamp attributes.
-/// Timestamps are specified in nanosecond-precision Unix time, more information can be found [here](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_tutorial/#timestamp).
+/// Borrowed form of [Tag], used by [crate::PointRef].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagRef<'a> {
+    pub name: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+}
+
+/// Borrowed form of [Field], used by [crate::PointRef].
 #[derive(Debug, Clone, PartialEq)]
+pub struct FieldRef<'a> {
+    pub name: Cow<'a, str>,
+    pub value: FieldDataRef<'a>,
+}
+
+/// Read access to a tag in either owned or borrowed form.
+pub(crate) trait AsTag {
+    fn name(&self) -> &str;
+    fn value(&self) -> &str;
+}
+
+/// Read access to a field in either owned or borrowed form.
+pub(crate) trait AsField {
+    fn name(&self) -> &str;
+    fn value(&self) -> FieldDataRef<'_>;
+}
+
+/// Settings applied when writing line protocol.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LineOptions {
+    pub precision: Precision,
+    pub unsigned: UnsignedMode,
+    pub field_order: FieldOrder,
+    /// Writes newlines in names and tag values as `\n` instead of
+    /// rejecting them, so points can always be displayed.
+    pub escape_newlines: bool,
+}
+
+/// Container struct for timestamp attributes.
+/// Timestamps are specified in Unix time, in the unit given by
+/// their [Precision]. More information can be found [here](https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_tutorial/#timestamp).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Timestamp {
     pub value: u64,
+    pub precision: Precision,
+}
+
+/// Unit of a Unix timestamp. Should match the `precision`
+/// setting of the Telegraf listener being written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Precision {
+    #[default]
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
 }
 
 impl LineProtocol {
+    /// Assembles a line from an unescaped measurement name and
+    /// already formatted tag, field and timestamp sections.
+    ///
+    /// Fails if the measurement contains a newline.
     pub fn new(
         measurement: String,
         tags: Option<String>,
         fields: String,
         timestamp: Option<String>,
-    ) -> Self {
+    ) -> Result<Self, TelegrafError> {
+        check_newline("measurement", &measurement)?;
+        let measurement = escape_measurement(&measurement);
         let mut lp = match tags {
             Some(t) => format!("{},{} {}", measurement, t, fields),
             None => format!("{} {}", measurement, fields),
@@ -65,7 +192,7 @@ impl LineProtocol {
         }
 
         lp.push('\n');
-        Self(lp)
+        Ok(Self(lp))
     }
 
     pub fn to_str(&self) -> &str {
@@ -73,6 +200,283 @@ impl LineProtocol {
     }
 }
 
+impl Timestamp {
+    pub fn new(value: u64, precision: Precision) -> Self {
+        Self { value, precision }
+    }
+
+    /// Converts the timestamp to another precision. Converting
+    /// to a coarser precision truncates. Returns `None` if the
+    /// value does not fit in the target precision.
+    pub fn to_precision(&self, precision: Precision) -> Option<u64> {
+        let nanos = self.value as u128 * self.precision.nanos() as u128;
+        u64::try_from(nanos / precision.nanos() as u128).ok()
+    }
+}
+
+impl From<u64> for Timestamp {
+    /// Creates a nanosecond precision timestamp.
+    fn from(value: u64) -> Self {
+        Self::new(value, Precision::Nanoseconds)
+    }
+}
+
+impl Precision {
+    /// Number of nanoseconds in one unit of this precision.
+    pub fn nanos(&self) -> u64 {
+        match self {
+            Precision::Nanoseconds => 1,
+            Precision::Microseconds => 1_000,
+            Precision::Milliseconds => 1_000_000,
+            Precision::Seconds => 1_000_000_000,
+        }
+    }
+
+    /// Short name of the precision as used in Telegraf
+    /// and InfluxDB configuration, e.g. `ms`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::Nanoseconds => "ns",
+            Precision::Microseconds => "us",
+            Precision::Milliseconds => "ms",
+            Precision::Seconds => "s",
+        }
+    }
+}
+
+impl UnsignedMode {
+    /// Converts field data to the representation used by
+    /// this mode. Only unsigned integers are affected.
+    pub fn convert(&self, value: FieldData) -> Result<FieldData, TelegrafError> {
+        match value {
+            FieldData::UNumber(n) => Ok(self
+                .signed(n)?
+                .map(FieldData::Number)
+                .unwrap_or(FieldData::UNumber(n))),
+            v => Ok(v),
+        }
+    }
+
+    /// Signed value to write an unsigned integer as, or
+    /// `None` if it is written natively.
+    fn signed(&self, n: u64) -> Result<Option<i64>, TelegrafError> {
+        match self {
+            UnsignedMode::Native => Ok(None),
+            UnsignedMode::Signed => i64::try_from(n).map(Some).map_err(|_| {
+                TelegrafError::BadProtocol(format!(
+                    "unsigned value {} does not fit in a signed integer",
+                    n
+                ))
+            }),
+            UnsignedMode::SignedSaturating => Ok(Some(i64::try_from(n).unwrap_or(i64::MAX))),
+        }
+    }
+}
+
+impl<'a> TagRef<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl<'a> FieldRef<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>, value: impl Into<FieldDataRef<'a>>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl AsTag for Tag {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl AsTag for TagRef<'_> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl AsField for Field {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self) -> FieldDataRef<'_> {
+        FieldDataRef::from(&self.value)
+    }
+}
+
+impl AsField for FieldRef<'_> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self) -> FieldDataRef<'_> {
+        match &self.value {
+            FieldDataRef::Str(s) => FieldDataRef::Str(Cow::Borrowed(s)),
+            v => v.clone(),
+        }
+    }
+}
+
+impl<'a> From<&'a FieldData> for FieldDataRef<'a> {
+    fn from(value: &'a FieldData) -> Self {
+        match value {
+            FieldData::Boolean(b) => FieldDataRef::Boolean(*b),
+            FieldData::UNumber(n) => FieldDataRef::UNumber(*n),
+            FieldData::Number(n) => FieldDataRef::Number(*n),
+            FieldData::Float(f) => FieldDataRef::Float(*f),
+            FieldData::Str(s) => FieldDataRef::Str(Cow::Borrowed(s)),
+        }
+    }
+}
+
+impl From<FieldData> for FieldDataRef<'_> {
+    fn from(value: FieldData) -> Self {
+        match value {
+            FieldData::Boolean(b) => FieldDataRef::Boolean(b),
+            FieldData::UNumber(n) => FieldDataRef::UNumber(n),
+            FieldData::Number(n) => FieldDataRef::Number(n),
+            FieldData::Float(f) => FieldDataRef::Float(f),
+            FieldData::Str(s) => FieldDataRef::Str(Cow::Owned(s)),
+        }
+    }
+}
+
+impl From<FieldDataRef<'_>> for FieldData {
+    fn from(value: FieldDataRef<'_>) -> Self {
+        match value {
+            FieldDataRef::Boolean(b) => FieldData::Boolean(b),
+            FieldDataRef::UNumber(n) => FieldData::UNumber(n),
+            FieldDataRef::Number(n) => FieldData::Number(n),
+            FieldDataRef::Float(f) => FieldData::Float(f),
+            FieldDataRef::Str(s) => FieldData::Str(s.into_owned()),
+        }
+    }
+}
+
+macro_rules! field_data_ref_from {
+    ($variant:ident, $as:ty, $($t:ty),+) => {
+        $(
+            impl From<$t> for FieldDataRef<'_> {
+                fn from(value: $t) -> Self {
+                    FieldDataRef::$variant(value as $as)
+                }
+            }
+        )+
+    };
+}
+
+field_data_ref_from!(UNumber, u64, u8, u16, u32, u64);
+field_data_ref_from!(Number, i64, i8, i16, i32, i64);
+field_data_ref_from!(Float, f64, f32, f64);
+
+impl From<bool> for FieldDataRef<'_> {
+    fn from(value: bool) -> Self {
+        FieldDataRef::Boolean(value)
+    }
+}
+
+impl<'a> From<&'a str> for FieldDataRef<'a> {
+    fn from(value: &'a str) -> Self {
+        FieldDataRef::Str(Cow::Borrowed(value))
+    }
+}
+
+impl<'a> From<&'a String> for FieldDataRef<'a> {
+    fn from(value: &'a String) -> Self {
+        FieldDataRef::Str(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for FieldDataRef<'_> {
+    fn from(value: String) -> Self {
+        FieldDataRef::Str(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for FieldDataRef<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        FieldDataRef::Str(value)
+    }
+}
+
+impl IntoTimestamp for Timestamp {
+    fn to_timestamp(&self, _: Precision) -> Result<Timestamp, TelegrafError> {
+        Ok(*self)
+    }
+}
+
+impl IntoTimestamp for u64 {
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        Ok(Timestamp::new(*self, precision))
+    }
+}
+
+impl IntoTimestamp for Duration {
+    /// Treats the duration as time elapsed since the Unix epoch.
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        from_unix_nanos(self.as_nanos() as i128, precision)
+    }
+}
+
+impl IntoTimestamp for SystemTime {
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        self.duration_since(UNIX_EPOCH)
+            .map_err(|_| pre_epoch())?
+            .to_timestamp(precision)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> IntoTimestamp for chrono::DateTime<Tz> {
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        let nanos =
+            self.timestamp() as i128 * 1_000_000_000 + self.timestamp_subsec_nanos() as i128;
+        from_unix_nanos(nanos, precision)
+    }
+}
+
+#[cfg(feature = "time")]
+impl IntoTimestamp for time::OffsetDateTime {
+    fn to_timestamp(&self, precision: Precision) -> Result<Timestamp, TelegrafError> {
+        from_unix_nanos(self.unix_timestamp_nanos(), precision)
+    }
+}
+
+fn from_unix_nanos(nanos: i128, precision: Precision) -> Result<Timestamp, TelegrafError> {
+    if nanos < 0 {
+        return Err(pre_epoch());
+    }
+    u64::try_from(nanos / precision.nanos() as i128)
+        .map(|v| Timestamp::new(v, precision))
+        .map_err(|_| {
+            TelegrafError::BadTimestamp(format!(
+                "time does not fit in a {} precision timestamp",
+                precision.as_str()
+            ))
+        })
+}
+
+fn pre_epoch() -> TelegrafError {
+    TelegrafError::BadTimestamp("time is before the Unix epoch".to_owned())
+}
+
 impl IntoFieldData for bool {
     fn field_data(&self) -> FieldData {
         FieldData::Boolean(*self)
@@ -143,12 +547,20 @@ impl IntoFieldData for &str {
     fn field_data(&self) -> FieldData {
         FieldData::Str(String::from(*self))
     }
+
+    fn field_data_ref(&self) -> FieldDataRef<'_> {
+        FieldDataRef::Str(Cow::Borrowed(self))
+    }
 }
 
 impl IntoFieldData for String {
     fn field_data(&self) -> FieldData {
         FieldData::Str(self.to_string())
     }
+
+    fn field_data_ref(&self) -> FieldDataRef<'_> {
+        FieldDataRef::Str(Cow::Borrowed(self))
+    }
 }
 
 pub fn get_field_string(value: &FieldData) -> String {
@@ -157,25 +569,282 @@ pub fn get_field_string(value: &FieldData) -> String {
         FieldData::UNumber(n) => format!("{}u", n),
         FieldData::Number(n) => format!("{}i", n),
         FieldData::Float(f) => format!("{}", f),
-        FieldData::Str(s) => format!(r#""{}""#, s),
+        FieldData::Str(s) => format!(r#""{}""#, escape_field_str(s)),
     }
 }
 
-pub fn format_attr(attrs: Vec<Attr>) -> String {
-    let mut out: Vec<String> = attrs
+/// Formats a set of attributes into their comma separated
+/// line protocol representation, escaping names and values.
+///
+/// Fails if a tag key, tag value or field key contains a
+/// newline, as line protocol has no way of escaping them.
+pub fn format_attr(attrs: Vec<Attr>) -> Result<String, TelegrafError> {
+    let (mut tags, rest): (Vec<Attr>, Vec<Attr>) =
+        attrs.into_iter().partition(|a| matches!(a, Attr::Tag(_)));
+    tags.sort_by(|a, b| match (a, b) {
+        (Attr::Tag(a), Attr::Tag(b)) => tag_order(a, b),
+        _ => Ordering::Equal,
+    });
+    let out: Vec<String> = tags
         .into_iter()
+        .chain(rest)
         .map(|a| match a {
-            Attr::Tag(t) => format!("{}={}", escape_spaces(&t.name), escape_spaces(&t.value)),
-            Attr::Field(f) => format!("{}={}", escape_spaces(&f.name), get_field_string(&f.value)),
-            Attr::Timestamp(t) => format!("{}", t.value),
+            Attr::Tag(t) => {
+                check_newline("tag key", &t.name)?;
+                check_newline("tag value", &t.value)?;
+                Ok(format!("{}={}", escape_key(&t.name), escape_key(&t.value)))
+            }
+            Attr::Field(f) => {
+                check_newline("field key", &f.name)?;
+                Ok(format!(
+                    "{}={}",
+                    escape_key(&f.name),
+                    get_field_string(&f.value)
+                ))
+            }
+            Attr::Timestamp(t) => Ok(format!("{}", t.value)),
         })
-        .collect();
-    out.sort();
-    out.join(",")
+        .collect::<Result<_, TelegrafError>>()?;
+    Ok(out.join(","))
+}
+
+/// Characters escaped in measurement names.
+const MEASUREMENT_SPECIAL: &[char] = &[',', ' '];
+/// Characters escaped in tag keys, tag values and field keys.
+const KEY_SPECIAL: &[char] = &[',', '=', ' '];
+/// Characters escaped in string field values.
+const STRING_SPECIAL: &[char] = &['"', '\\'];
+
+/// Escapes a measurement name. Commas and spaces
+/// are escaped with a backslash.
+pub fn escape_measurement(s: &str) -> String {
+    escaped(s, MEASUREMENT_SPECIAL).collect()
+}
+
+/// Escapes a tag key, tag value or field key. Commas,
+/// equals signs and spaces are escaped with a backslash.
+pub fn escape_key(s: &str) -> String {
+    escaped(s, KEY_SPECIAL).collect()
+}
+
+/// Escapes the contents of a string field value. Double
+/// quotes and backslashes are escaped with a backslash.
+pub fn escape_field_str(s: &str) -> String {
+    escaped(s, STRING_SPECIAL).collect()
+}
+
+fn escaped<'a>(s: &'a str, special: &'a [char]) -> impl Iterator<Item = char> + 'a {
+    s.chars().flat_map(move |c| {
+        let esc = special.contains(&c);
+        iter::once('\\').filter(move |_| esc).chain(iter::once(c))
+    })
+}
+
+/// Writes a measurement, key or tag value, with any newlines
+/// written as `\n` if `escape_newlines` is set.
+fn write_key<W: fmt::Write>(
+    w: &mut W,
+    s: &str,
+    special: &[char],
+    escape_newlines: bool,
+) -> fmt::Result {
+    if !escape_newlines {
+        return write_escaped(w, s, special);
+    }
+    for (i, part) in s.split('\n').enumerate() {
+        if i > 0 {
+            w.write_str("\\n")?;
+        }
+        write_escaped(w, part, special)?;
+    }
+    Ok(())
+}
+
+fn write_escaped<W: fmt::Write>(w: &mut W, s: &str, special: &[char]) -> fmt::Result {
+    let mut run = 0;
+    for (i, c) in s.char_indices() {
+        if special.contains(&c) {
+            w.write_str(&s[run..i])?;
+            w.write_char('\\')?;
+            run = i;
+        }
+    }
+    w.write_str(&s[run..])
+}
+
+/// Writes a single line of line protocol. Tags are ordered by
+/// key and fields as per [LineOptions::field_order], sorting indices
+/// in `scratch` so no allocation is needed once it has grown.
+///
+/// Everything that can fail is checked before anything
+/// is written.
+pub(crate) fn write_line<W, T, F>(
+    w: &mut W,
+    measurement: &str,
+    tags: &[T],
+    fields: &[F],
+    timestamp: Option<Timestamp>,
+    opts: &LineOptions,
+    scratch: &mut Vec<usize>,
+) -> Result<(), TelegrafError>
+where
+    W: fmt::Write,
+    T: AsTag,
+    F: AsField,
+{
+    check_newline("measurement", measurement)?;
+    for t in tags {
+        check_newline("tag key", t.name())?;
+        check_newline("tag value", t.value())?;
+    }
+    for f in fields {
+        check_newline("field key", f.name())?;
+        if let FieldDataRef::UNumber(n) = f.value() {
+            opts.unsigned.signed(n)?;
+        }
+    }
+    let timestamp = match timestamp {
+        Some(t) => Some(t.to_precision(opts.precision).ok_or_else(|| {
+            TelegrafError::BadProtocol(format!(
+                "timestamp {} does not fit in {} precision",
+                t.value,
+                opts.precision.as_str()
+            ))
+        })?),
+        None => None,
+    };
+
+    write_with(w, |w| {
+        write_line_unchecked(w, measurement, tags, fields, timestamp, opts, scratch)
+    })
+}
+
+/// Writes a line as [write_line] does in the precision of
+/// `timestamp`, but showing newlines that cannot be escaped
+/// as `\n` instead of failing. Used for [fmt::Display].
+pub(crate) fn display_line<W, T, F>(
+    w: &mut W,
+    measurement: &str,
+    tags: &[T],
+    fields: &[F],
+    timestamp: Option<Timestamp>,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    T: AsTag,
+    F: AsField,
+{
+    let opts = LineOptions {
+        escape_newlines: true,
+        ..LineOptions::default()
+    };
+    let timestamp = timestamp.map(|t| t.value);
+    write_line_unchecked(
+        w,
+        measurement,
+        tags,
+        fields,
+        timestamp,
+        &opts,
+        &mut Vec::new(),
+    )
+}
+
+/// Writes the escaped measurement and tags sorted by key,
+/// which together identify a series.
+pub(crate) fn write_series_key<W: fmt::Write, T: AsTag>(
+    w: &mut W,
+    measurement: &str,
+    tags: &[T],
+    escape_newlines: bool,
+    scratch: &mut Vec<usize>,
+) -> fmt::Result {
+    write_key(w, measurement, MEASUREMENT_SPECIAL, escape_newlines)?;
+
+    scratch.clear();
+    scratch.extend(0..tags.len());
+    scratch.sort_by(|&a, &b| tag_order(&tags[a], &tags[b]));
+    for &i in scratch.iter() {
+        w.write_char(',')?;
+        write_key(w, tags[i].name(), KEY_SPECIAL, escape_newlines)?;
+        w.write_char('=')?;
+        write_key(w, tags[i].value(), KEY_SPECIAL, escape_newlines)?;
+    }
+    Ok(())
+}
+
+fn write_line_unchecked<W, T, F>(
+    w: &mut W,
+    measurement: &str,
+    tags: &[T],
+    fields: &[F],
+    timestamp: Option<u64>,
+    opts: &LineOptions,
+    scratch: &mut Vec<usize>,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    T: AsTag,
+    F: AsField,
+{
+    write_series_key(w, measurement, tags, opts.escape_newlines, scratch)?;
+
+    scratch.clear();
+    scratch.extend(0..fields.len());
+    if opts.field_order == FieldOrder::ByKey {
+        scratch.sort_by(|&a, &b| fields[a].name().cmp(fields[b].name()));
+    }
+    for (n, &i) in scratch.iter().enumerate() {
+        w.write_char(if n == 0 { ' ' } else { ',' })?;
+        write_key(w, fields[i].name(), KEY_SPECIAL, opts.escape_newlines)?;
+        w.write_char('=')?;
+        write_field_value(w, &fields[i].value(), opts.unsigned)?;
+    }
+
+    if let Some(ts) = timestamp {
+        write!(w, " {}", ts)?;
+    }
+    w.write_char('\n')
+}
+
+fn write_field_value<W: fmt::Write>(
+    w: &mut W,
+    value: &FieldDataRef<'_>,
+    unsigned: UnsignedMode,
+) -> fmt::Result {
+    match value {
+        FieldDataRef::Boolean(b) => write!(w, "{}", b),
+        FieldDataRef::UNumber(n) => match unsigned.signed(*n) {
+            Ok(Some(i)) => write!(w, "{}i", i),
+            _ => write!(w, "{}u", n),
+        },
+        FieldDataRef::Number(n) => write!(w, "{}i", n),
+        FieldDataRef::Float(f) => write!(w, "{}", f),
+        FieldDataRef::Str(s) => {
+            w.write_char('"')?;
+            write_escaped(w, s, STRING_SPECIAL)?;
+            w.write_char('"')
+        }
+    }
 }
 
-fn escape_spaces(s: &str) -> String {
-    s.replace(' ', r"\ ")
+/// Orders tags by key byte order, as recommended by InfluxDB.
+/// Values only break ties between duplicate keys, keeping
+/// the output deterministic.
+pub(crate) fn tag_order<T: AsTag>(a: &T, b: &T) -> Ordering {
+    a.name()
+        .cmp(b.name())
+        .then_with(|| a.value().cmp(b.value()))
+}
+
+fn check_newline(element: &str, s: &str) -> Result<(), TelegrafError> {
+    if s.contains('\n') {
+        return Err(TelegrafError::BadProtocol(format!(
+            "{} {:?} contains a newline, which cannot be escaped",
+            element, s
+        )));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -233,7 +902,127 @@ mod tests {
                 value: FieldData::Str(String::from("2")),
             }),
         ];
-        assert_eq!(format_attr(v1), String::from("f1=1i,f2=2i"));
-        assert_eq!(format_attr(v2), String::from("f1=1i,f2=\"2\""));
+        assert_eq!(format_attr(v1).unwrap(), String::from("f1=1i,f2=2i"));
+        assert_eq!(format_attr(v2).unwrap(), String::from("f1=1i,f2=\"2\""));
+    }
+
+    #[test]
+    fn can_convert_timestamp_precision() {
+        let ts = Timestamp::new(1_500, Precision::Milliseconds);
+        assert_eq!(ts.to_precision(Precision::Nanoseconds), Some(1_500_000_000));
+        assert_eq!(ts.to_precision(Precision::Microseconds), Some(1_500_000));
+        assert_eq!(ts.to_precision(Precision::Milliseconds), Some(1_500));
+        assert_eq!(ts.to_precision(Precision::Seconds), Some(1));
+
+        let ts = Timestamp::new(u64::MAX, Precision::Seconds);
+        assert_eq!(ts.to_precision(Precision::Nanoseconds), None);
+    }
+
+    #[test]
+    fn can_convert_into_timestamp() {
+        let ms = Precision::Milliseconds;
+        assert_eq!(10u64.to_timestamp(ms).unwrap(), Timestamp::new(10, ms));
+        assert_eq!(
+            Duration::from_micros(1_500).to_timestamp(ms).unwrap(),
+            Timestamp::new(1, ms)
+        );
+        assert_eq!(
+            (UNIX_EPOCH + Duration::from_secs(2))
+                .to_timestamp(Precision::Nanoseconds)
+                .unwrap(),
+            Timestamp::new(2_000_000_000, Precision::Nanoseconds)
+        );
+        assert_eq!(
+            Timestamp::new(5, Precision::Seconds)
+                .to_timestamp(ms)
+                .unwrap(),
+            Timestamp::new(5, Precision::Seconds)
+        );
+    }
+
+    #[test]
+    fn cannot_convert_pre_epoch_timestamp() {
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert!(before.to_timestamp(Precision::Seconds).is_err());
+        assert!(Duration::MAX.to_timestamp(Precision::Nanoseconds).is_err());
+    }
+
+    #[test]
+    fn can_convert_unsigned() {
+        let small = FieldData::UNumber(10);
+        let big = FieldData::UNumber(u64::MAX);
+        assert_eq!(UnsignedMode::Native.convert(big.clone()).unwrap(), big);
+        assert_eq!(
+            UnsignedMode::Signed.convert(small.clone()).unwrap(),
+            FieldData::Number(10)
+        );
+        assert!(UnsignedMode::Signed.convert(big.clone()).is_err());
+        assert_eq!(
+            UnsignedMode::SignedSaturating.convert(small).unwrap(),
+            FieldData::Number(10)
+        );
+        assert_eq!(
+            UnsignedMode::SignedSaturating.convert(big).unwrap(),
+            FieldData::Number(i64::MAX)
+        );
+        assert_eq!(
+            UnsignedMode::Signed.convert(FieldData::Float(1.5)).unwrap(),
+            FieldData::Float(1.5)
+        );
+    }
+
+    #[test]
+    fn can_escape_string_field() {
+        let s = get_field_string(&FieldData::Str(String::from(r#"a "b" \c"#)));
+        assert_eq!(s, String::from(r#""a \"b\" \\c""#));
+    }
+
+    #[test]
+    fn can_escape_tag_attr() {
+        let v: Vec<Attr> = vec![Attr::Tag(Tag {
+            name: String::from("t k=1"),
+            value: String::from("a,b=c d"),
+        })];
+        assert_eq!(format_attr(v).unwrap(), String::from(r"t\ k\=1=a\,b\=c\ d"));
+    }
+
+    #[test]
+    fn can_escape_field_key_attr() {
+        let v: Vec<Attr> = vec![Attr::Field(Field {
+            name: String::from("f,1 =x"),
+            value: FieldData::Number(1),
+        })];
+        assert_eq!(format_attr(v).unwrap(), String::from(r"f\,1\ \=x=1i"));
+    }
+
+    #[test]
+    fn can_escape_measurement() {
+        let lp = LineProtocol::new(String::from("m,1 2=3"), None, String::from("f=1i"), None);
+        assert_eq!(lp.unwrap().to_str(), "m\\,1\\ 2=3 f=1i\n");
+    }
+
+    #[test]
+    fn rejects_newlines() {
+        let tag = vec![Attr::Tag(Tag {
+            name: String::from("t"),
+            value: String::from("a\nb"),
+        })];
+        let field = vec![Attr::Field(Field {
+            name: String::from("f\n"),
+            value: FieldData::Number(1),
+        })];
+        let lp = LineProtocol::new(String::from("m\n"), None, String::from("f=1i"), None);
+        assert!(format_attr(tag).is_err());
+        assert!(format_attr(field).is_err());
+        assert!(lp.is_err());
+    }
+
+    #[test]
+    fn allows_newlines_in_string_fields() {
+        let v = vec![Attr::Field(Field {
+            name: String::from("f"),
+            value: FieldData::Str(String::from("a\nb")),
+        })];
+        assert_eq!(format_attr(v).unwrap(), String::from("f=\"a\nb\""));
     }
 }
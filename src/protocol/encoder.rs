@@ -0,0 +1,213 @@
+use std::{fmt, io};
+
+use super::{write_line, FieldOrder, LineOptions, Precision, Timestamp, UnsignedMode};
+use crate::{
+    format::{Bytes, IoWriter, Serializer},
+    Point, PointRef, TelegrafError,
+};
+
+/// Streaming line protocol encoder.
+///
+/// Appends the line protocol of each point straight into a
+/// [fmt::Write] or [io::Write], without building intermediate
+/// strings. The encoder keeps a little scratch space for ordering
+/// tags and fields, so reusing one encoder (and one output buffer)
+/// across points makes encoding allocation-free once warmed up.
+///
+/// Points are not validated; use [crate::Point::validate] first
+/// if they come from untrusted input. Encoding fails before
+/// anything is written if a point cannot be represented, e.g.
+/// because a tag contains a newline.
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::{point, LineEncoder, Precision};
+///
+/// let mut enc = LineEncoder::new().with_precision(Precision::Seconds);
+/// let mut buf = String::new();
+/// for i in 0..2 {
+///     let p = point!("m", ("f", i); 1_000_000_000u64 * i as u64);
+///     enc.encode_point(&mut buf, &p).unwrap();
+/// }
+/// assert_eq!(buf, "m f=0i 0\nm f=1i 1\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LineEncoder {
+    opts: LineOptions,
+    scratch: Vec<usize>,
+}
+
+impl LineEncoder {
+    /// Creates an encoder writing nanosecond timestamps and
+    /// native unsigned integers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the precision timestamps are written in. Timestamps
+    /// of other precisions are converted.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.opts.precision = precision;
+        self
+    }
+
+    /// Sets how unsigned integer fields are written.
+    pub fn with_unsigned_mode(mut self, mode: UnsignedMode) -> Self {
+        self.opts.unsigned = mode;
+        self
+    }
+
+    /// Sets the order fields are written in.
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.opts.field_order = order;
+        self
+    }
+
+    /// Appends a point as a line of line protocol.
+    pub fn encode_point<W: fmt::Write>(
+        &mut self,
+        w: &mut W,
+        pt: &Point,
+    ) -> Result<(), TelegrafError> {
+        self.encode_point_at(w, pt, pt.timestamp)
+    }
+
+    /// Appends a point with `timestamp` in place of its own.
+    pub(crate) fn encode_point_at<W: fmt::Write>(
+        &mut self,
+        w: &mut W,
+        pt: &Point,
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TelegrafError> {
+        write_line(
+            w,
+            &pt.measurement,
+            &pt.tags,
+            &pt.fields,
+            timestamp,
+            &self.opts,
+            &mut self.scratch,
+        )
+    }
+
+    /// Appends a borrowed point as a line of line protocol.
+    pub fn encode_point_ref<W: fmt::Write>(
+        &mut self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        write_line(
+            w,
+            &pt.measurement,
+            &pt.tags,
+            &pt.fields,
+            pt.timestamp,
+            &self.opts,
+            &mut self.scratch,
+        )
+    }
+
+    /// Writes a point as a line of line protocol. The line is
+    /// written in several small pieces, so unbuffered writers
+    /// should be wrapped in an [io::BufWriter].
+    pub fn write_point<W: io::Write>(
+        &mut self,
+        w: &mut W,
+        pt: &Point,
+    ) -> Result<(), TelegrafError> {
+        let mut w = IoWriter::new(w);
+        let res = self.encode_point(&mut w, pt);
+        w.finish(res)
+    }
+
+    /// Writes a borrowed point as per [LineEncoder::write_point].
+    pub fn write_point_ref<W: io::Write>(
+        &mut self,
+        w: &mut W,
+        pt: &PointRef,
+    ) -> Result<(), TelegrafError> {
+        let mut w = IoWriter::new(w);
+        let res = self.encode_point_ref(&mut w, pt);
+        w.finish(res)
+    }
+}
+
+impl Serializer for LineEncoder {
+    fn serialize(&mut self, buf: &mut Vec<u8>, pt: &PointRef) -> Result<(), TelegrafError> {
+        self.encode_point_ref(&mut Bytes(buf), pt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, point_ref, Timestamp};
+
+    #[test]
+    fn can_encode_points() {
+        let mut enc = LineEncoder::new();
+        let mut buf = String::new();
+        enc.encode_point(&mut buf, &point!("m", ("t", "v"), ("f", 1); 10))
+            .unwrap();
+        enc.encode_point_ref(&mut buf, &point_ref!("m", ("f", "s")))
+            .unwrap();
+        assert_eq!(buf, "m,t=v f=1i 10\nm f=\"s\"\n");
+    }
+
+    #[test]
+    fn can_encode_with_options() {
+        let mut enc = LineEncoder::new()
+            .with_precision(Precision::Seconds)
+            .with_unsigned_mode(UnsignedMode::SignedSaturating);
+        let mut buf = String::new();
+        let ts = Timestamp::new(2_000, Precision::Milliseconds);
+        enc.encode_point(&mut buf, &point!("m", ("f", u64::MAX); ts))
+            .unwrap();
+        assert_eq!(buf, format!("m f={}i 2\n", i64::MAX));
+
+        let mut enc = enc.with_unsigned_mode(UnsignedMode::Signed);
+        let res = enc.encode_point(&mut String::new(), &point!("m", ("f", u64::MAX)));
+        assert!(matches!(res, Err(TelegrafError::BadProtocol(_))));
+    }
+
+    #[test]
+    fn can_encode_in_field_order() {
+        let p = point!("m", ("b", "2")("a", "1")("B", "3"), ("y", 1)("x", 2));
+        let mut buf = String::new();
+        LineEncoder::new().encode_point(&mut buf, &p).unwrap();
+        LineEncoder::new()
+            .with_field_order(FieldOrder::ByKey)
+            .encode_point(&mut buf, &p)
+            .unwrap();
+        assert_eq!(buf, "m,B=3,a=1,b=2 y=1i,x=2i\nm,B=3,a=1,b=2 x=2i,y=1i\n");
+    }
+
+    #[test]
+    fn can_write_points() {
+        let mut enc = LineEncoder::new();
+        let mut out = Vec::new();
+        enc.write_point(&mut out, &point!("m", ("f", 1.5))).unwrap();
+        enc.write_point_ref(&mut out, &point_ref!("m", ("f", true)))
+            .unwrap();
+        assert_eq!(out, b"m f=1.5\nm f=true\n");
+    }
+
+    #[test]
+    fn reports_io_errors() {
+        let mut out = [0u8; 4];
+        let res = LineEncoder::new().write_point(&mut &mut out[..], &point!("m", ("f", 1.5)));
+        match res {
+            Err(TelegrafError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn does_not_write_unencodable_points() {
+        let mut buf = String::from("x");
+        let res = LineEncoder::new().encode_point(&mut buf, &point!("m", ("t", "a\nb"), ("f", 1)));
+        assert!(res.is_err());
+        assert_eq!(buf, "x");
+    }
+}
@@ -0,0 +1,570 @@
+use std::{fmt, io::BufRead};
+
+use super::{Field, FieldData, Tag, Timestamp};
+use crate::{Point, TelegrafError};
+
+/// Error produced when line protocol cannot be parsed.
+///
+/// Lines and columns are 1-based. Columns count
+/// characters from the start of the offending line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Longest record a [PointReader] buffers while waiting for the
+/// closing quote of a multi-line string field.
+const MAX_RECORD_LEN: usize = 64 * 1024;
+
+/// Iterator over the points of a line protocol stream.
+///
+/// Created via [parse_reader]. Blank lines and comments
+/// are skipped.
+pub struct PointReader<R> {
+    reader: R,
+    buf: String,
+    line: usize,
+}
+
+/// Parses a single line of line protocol.
+///
+/// Returns `None` for blank lines and comments. A trailing
+/// newline is permitted, any further lines are an error.
+pub fn parse_line(line: &str) -> Result<Option<Point>, ParseError> {
+    let mut parser = Parser::new(line);
+    let point = parser.record().map_err(|e| e.locate(line, 0, 1))?;
+    if parser.pos < line.len() {
+        return Err(parser
+            .error("unexpected data after end of line")
+            .locate(line, 0, 1));
+    }
+    Ok(point)
+}
+
+/// Parses every point in a block of line protocol text,
+/// skipping blank lines and comments.
+pub fn parse(input: &str) -> Result<Vec<Point>, ParseError> {
+    let mut parser = Parser::new(input);
+    let mut points = Vec::new();
+    let mut line = 1;
+    while parser.pos < input.len() {
+        let start = parser.pos;
+        if let Some(p) = parser.record().map_err(|e| e.locate(input, start, line))? {
+            points.push(p);
+        }
+        line += count_newlines(&input[start..parser.pos]);
+    }
+    Ok(points)
+}
+
+/// Lazily parses points from a buffered reader.
+///
+/// Each item is either a parsed point or the first error
+/// encountered on a line. Read failures are reported as
+/// [crate::TelegrafError::IoError]. String fields may span
+/// lines, but a record still missing its closing quote after
+/// 64 KiB is reported as an error and skipped.
+pub fn parse_reader<R: BufRead>(reader: R) -> PointReader<R> {
+    PointReader {
+        reader,
+        buf: String::new(),
+        line: 1,
+    }
+}
+
+impl<R: BufRead> Iterator for PointReader<R> {
+    type Item = Result<Point, TelegrafError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            let mut eof = false;
+            // Opening quote of a string field left open by an
+            // earlier line, and where to look for its closing quote.
+            let mut open = None;
+            let result = loop {
+                match self.reader.read_line(&mut self.buf) {
+                    Ok(0) => eof = true,
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(TelegrafError::IoError(e))),
+                }
+                if eof && self.buf.is_empty() {
+                    return None;
+                }
+
+                if let Some((start, from)) = open {
+                    match find_closing_quote(&self.buf, from) {
+                        Err(resume) if !eof && self.buf.len() <= MAX_RECORD_LEN => {
+                            open = Some((start, resume));
+                            continue;
+                        }
+                        Err(_) if !eof => {
+                            break Err(RawError {
+                                pos: start,
+                                message: format!(
+                                    "string field value exceeds {} bytes",
+                                    MAX_RECORD_LEN
+                                ),
+                                incomplete: false,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+
+                let mut parser = Parser::new(&self.buf);
+                match parser.record() {
+                    // String fields may span multiple lines, keep
+                    // reading until the closing quote shows up.
+                    Err(e) if e.incomplete && !eof => {
+                        open = Some((e.pos, e.pos + 1));
+                        continue;
+                    }
+                    r => break r,
+                }
+            };
+
+            let line = self.line;
+            self.line += count_newlines(&self.buf);
+            match result {
+                Ok(Some(p)) => return Some(Ok(p)),
+                Ok(None) => continue,
+                Err(e) => {
+                    let e = e.locate(&self.buf, 0, line);
+                    return Some(Err(TelegrafError::ParseError(e)));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn count_newlines(s: &str) -> usize {
+    s.bytes().filter(|b| *b == b'\n').count()
+}
+
+/// Finds the quote closing a string field, scanning from `from`
+/// with the escapes of [Parser::string_value]. Returns where to
+/// resume scanning once more input has been read if there is none.
+fn find_closing_quote(s: &str, from: usize) -> Result<usize, usize> {
+    let bytes = s.as_bytes();
+    let mut pos = from;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => return Ok(pos),
+            b'\\' if pos + 1 == bytes.len() => return Err(pos),
+            b'\\' if matches!(bytes[pos + 1], b'"' | b'\\') => pos += 2,
+            _ => pos += 1,
+        }
+    }
+    Err(pos)
+}
+
+/// Error with a byte offset that has not yet been
+/// resolved to a line and column.
+struct RawError {
+    pos: usize,
+    message: String,
+    incomplete: bool,
+}
+
+impl RawError {
+    fn locate(self, input: &str, start: usize, line: usize) -> ParseError {
+        let before = &input[start..self.pos];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        ParseError {
+            line: line + count_newlines(before),
+            column: before[line_start..].chars().count() + 1,
+            message: self.message,
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.as_bytes().get(self.pos + offset).copied()
+    }
+
+    fn error(&self, message: &str) -> RawError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: &str) -> RawError {
+        RawError {
+            pos,
+            message: message.to_owned(),
+            incomplete: false,
+        }
+    }
+
+    fn at_eol(&self) -> bool {
+        match self.peek() {
+            None | Some(b'\n') => true,
+            Some(b'\r') => self.peek_at(1) == Some(b'\n'),
+            _ => false,
+        }
+    }
+
+    fn skip_eol(&mut self) {
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_spaces(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn expect(&mut self, b: u8, message: &str) -> Result<(), RawError> {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    /// Parses one record, consuming its terminating newline.
+    fn record(&mut self) -> Result<Option<Point>, RawError> {
+        self.skip_spaces();
+        if self.peek() == Some(b'#') {
+            while !self.at_eol() {
+                self.pos += 1;
+            }
+        }
+        if self.at_eol() {
+            self.skip_eol();
+            return Ok(None);
+        }
+
+        let measurement = self.token(b", ", b", ", "measurement")?;
+
+        let mut tags = Vec::new();
+        while self.peek() == Some(b',') {
+            self.pos += 1;
+            let name = self.token(b",= ", b",= ", "tag key")?;
+            self.expect(b'=', "expected '=' after tag key")?;
+            let value = self.token(b", ", b",= ", "tag value")?;
+            tags.push(Tag { name, value });
+        }
+
+        if self.skip_spaces() == 0 || self.at_eol() {
+            return Err(self.error("expected field set"));
+        }
+
+        let mut fields = Vec::new();
+        loop {
+            let name = self.token(b",= ", b",= ", "field key")?;
+            self.expect(b'=', "expected '=' after field key")?;
+            let value = self.field_value()?;
+            fields.push(Field { name, value });
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        let mut timestamp = None;
+        if !self.at_eol() {
+            if self.skip_spaces() == 0 {
+                return Err(self.error("expected space after field set"));
+            }
+            if !self.at_eol() {
+                timestamp = Some(self.timestamp()?);
+                self.skip_spaces();
+            }
+        }
+
+        if !self.at_eol() {
+            return Err(self.error("unexpected data after timestamp"));
+        }
+        self.skip_eol();
+
+        Ok(Some(Point {
+            measurement,
+            tags,
+            fields,
+            timestamp,
+        }))
+    }
+
+    /// Reads an identifier up to the first unescaped byte in `stops`,
+    /// resolving backslash escapes of the bytes in `escapable`.
+    fn token(&mut self, stops: &[u8], escapable: &[u8], what: &str) -> Result<String, RawError> {
+        let start = self.pos;
+        let mut out = String::new();
+        let mut run = self.pos;
+        while !self.at_eol() {
+            let b = self.input.as_bytes()[self.pos];
+            if b == b'\\' && self.peek_at(1).is_some_and(|n| escapable.contains(&n)) {
+                out.push_str(&self.input[run..self.pos]);
+                run = self.pos + 1;
+                self.pos += 2;
+            } else if stops.contains(&b) {
+                break;
+            } else {
+                self.pos += 1;
+            }
+        }
+        out.push_str(&self.input[run..self.pos]);
+
+        if out.is_empty() {
+            return Err(self.error_at(start, &format!("missing {}", what)));
+        }
+        Ok(out)
+    }
+
+    fn field_value(&mut self) -> Result<FieldData, RawError> {
+        if self.peek() == Some(b'"') {
+            return self.string_value();
+        }
+
+        let start = self.pos;
+        while !self.at_eol() && !matches!(self.peek(), Some(b',') | Some(b' ') | Some(b'\t')) {
+            self.pos += 1;
+        }
+        let raw = &self.input[start..self.pos];
+        let invalid = |kind: &str| self.error_at(start, &format!("invalid {} {:?}", kind, raw));
+
+        if raw.is_empty() {
+            return Err(self.error_at(start, "missing field value"));
+        }
+        if let Some(n) = raw.strip_suffix('i') {
+            return n
+                .parse()
+                .map(FieldData::Number)
+                .map_err(|_| invalid("integer"));
+        }
+        if let Some(n) = raw.strip_suffix('u') {
+            return n
+                .parse()
+                .map(FieldData::UNumber)
+                .map_err(|_| invalid("unsigned integer"));
+        }
+        match raw {
+            "t" | "T" | "true" | "True" | "TRUE" => return Ok(FieldData::Boolean(true)),
+            "f" | "F" | "false" | "False" | "FALSE" => return Ok(FieldData::Boolean(false)),
+            _ => {}
+        }
+
+        // Rust accepts spellings such as `inf` and `NaN`
+        // which line protocol does not.
+        if !raw
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+        {
+            return Err(invalid("field value"));
+        }
+        match raw.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(FieldData::Float(f)),
+            _ => Err(invalid("float")),
+        }
+    }
+
+    fn string_value(&mut self) -> Result<FieldData, RawError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = String::new();
+        let mut run = self.pos;
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(RawError {
+                        pos: start,
+                        message: "unterminated string field value".to_owned(),
+                        incomplete: true,
+                    })
+                }
+                Some(b'"') => break,
+                Some(b'\\') if matches!(self.peek_at(1), Some(b'"') | Some(b'\\')) => {
+                    out.push_str(&self.input[run..self.pos]);
+                    run = self.pos + 1;
+                    self.pos += 2;
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+        out.push_str(&self.input[run..self.pos]);
+        self.pos += 1;
+        Ok(FieldData::Str(out))
+    }
+
+    fn timestamp(&mut self) -> Result<Timestamp, RawError> {
+        let start = self.pos;
+        while !self.at_eol() && !matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+            self.pos += 1;
+        }
+        let raw = &self.input[start..self.pos];
+        if raw.starts_with('-') {
+            return Err(self.error_at(start, "negative timestamps are not supported"));
+        }
+        if !raw.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(self.error_at(start, &format!("invalid timestamp {:?}", raw)));
+        }
+        raw.parse::<u64>()
+            .map(Timestamp::from)
+            .map_err(|_| self.error_at(start, "timestamp out of range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+
+    #[test]
+    fn can_parse_point() {
+        let p = parse_line("m,t1=a,t2=b f1=1i,f2=2u,f3=1.5,f4=\"s\",f5=t 10\n").unwrap();
+        let exp = point!("m", ("t1", "a")("t2", "b"), ("f1", 1i64)("f2", 2u64)("f3", 1.5)("f4", "s")("f5", true); 10);
+        assert_eq!(p, Some(exp));
+    }
+
+    #[test]
+    fn can_parse_without_tags_or_timestamp() {
+        let p = parse_line("m f=-2.5e3").unwrap();
+        assert_eq!(p, Some(point!("m", ("f", -2500.))));
+    }
+
+    #[test]
+    fn can_parse_booleans() {
+        for (s, b) in [
+            ("t", true),
+            ("T", true),
+            ("true", true),
+            ("True", true),
+            ("TRUE", true),
+            ("f", false),
+            ("F", false),
+            ("false", false),
+            ("False", false),
+            ("FALSE", false),
+        ] {
+            let p = parse_line(&format!("m f={}", s)).unwrap().unwrap();
+            assert_eq!(p.fields[0].value, FieldData::Boolean(b));
+        }
+    }
+
+    #[test]
+    fn can_parse_escapes() {
+        let p = parse_line(r#"m\,1\ 2,t\ k\=1=a\,b\=c\ d f\=1="say \"hi\" \\o/" 5"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(p.measurement, "m,1 2");
+        assert_eq!(p.tags[0].name, "t k=1");
+        assert_eq!(p.tags[0].value, "a,b=c d");
+        assert_eq!(p.fields[0].name, "f=1");
+        assert_eq!(
+            p.fields[0].value,
+            FieldData::Str(r#"say "hi" \o/"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn can_parse_multiple_lines() {
+        let input = "# comment\n\nm f=1i 1\n  \nm,t=v f=\"a\nb\" 2\r\nm f=3i\n";
+        let points = parse(input).unwrap();
+        assert_eq!(
+            points,
+            vec![
+                point!("m", ("f", 1i64); 1),
+                point!("m", ("t", "v"), ("f", "a\nb"); 2),
+                point!("m", ("f", 3i64)),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_parse_reader() {
+        let input = "m f=1i\n# comment\nm f=\"a\nb\"\nm f=x\n";
+        let mut r = parse_reader(input.as_bytes());
+        assert_eq!(r.next().unwrap().unwrap(), point!("m", ("f", 1i64)));
+        assert_eq!(r.next().unwrap().unwrap(), point!("m", ("f", "a\nb")));
+        match r.next().unwrap() {
+            Err(TelegrafError::ParseError(e)) => assert_eq!((e.line, e.column), (5, 5)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn limits_unterminated_strings() {
+        let mut input = String::from("m f=\"a\\\nb\n");
+        for _ in 0..MAX_RECORD_LEN / 7 {
+            input.push_str("m f=1i\n");
+        }
+        input.push_str("m f=\"c\n\"\n");
+        let mut r = parse_reader(input.as_bytes());
+        match r.next().unwrap() {
+            Err(TelegrafError::ParseError(e)) => assert_eq!((e.line, e.column), (1, 5)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(r.next().unwrap().unwrap(), point!("m", ("f", 1i64)));
+        assert_eq!(r.last().unwrap().unwrap(), point!("m", ("f", "c\n")));
+    }
+
+    #[test]
+    fn can_round_trip_encoded_point() {
+        let p = point!("m x", ("t,1", "a=b"), ("a 1", "q\"\\")("f2", -3i64)("f3", 4u64); 100);
+        assert_eq!(parse(&p.to_string()).unwrap(), vec![p]);
+    }
+
+    #[test]
+    fn reports_error_location() {
+        let err = parse("m f=1i\nm,t=v f=1x\n").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 9));
+
+        let err = parse_line("m,t f=1").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 4));
+
+        let err = parse_line("m").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 2));
+
+        let err = parse_line("m f=\"abc").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 5));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(parse_line("m f=inf").is_err());
+        assert!(parse_line("m f=NaN").is_err());
+        assert!(parse_line("m f=1.5i").is_err());
+        assert!(parse_line("m f=-1u").is_err());
+        assert!(parse_line("m f=1 -5").is_err());
+        assert!(parse_line("m f=1 5 6").is_err());
+        assert!(parse_line("m f=1\nm f=2").is_err());
+    }
+}
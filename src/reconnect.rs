@@ -0,0 +1,415 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{Connector, TelegrafError, TelegrafResult};
+
+/// Reconnection policy for `tcp://`, `tls://` and `unix://` connections,
+/// used with [crate::Client::with_reconnect].
+///
+/// When a write fails because the connection broke, e.g. after
+/// Telegraf restarted, the client resolves the URL again and
+/// reconnects. The first attempt is made on the next write, or
+/// straight away when [Reconnect::with_write_retry] is set. Failed
+/// attempts are spaced out with exponential backoff, and writes in
+/// between fail fast with [TelegrafError::ConnectionError] instead of
+/// blocking.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use telegraf::{Client, Reconnect, ReconnectEvent};
+///
+/// let reconnect = Reconnect::new()
+///     .with_max_backoff(Duration::from_secs(10))
+///     .with_write_retry(true)
+///     .with_listener(|e: &ReconnectEvent| eprintln!("telegraf: {}", e));
+/// let client = Client::new("tcp://localhost:8094")
+///     .unwrap()
+///     .with_reconnect(reconnect);
+/// ```
+pub struct Reconnect {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retry_write: bool,
+    listener: Option<Listener>,
+}
+
+type Listener = Box<dyn FnMut(&ReconnectEvent) + Send>;
+
+/// Change of a connection's state, reported to the listener
+/// of a [Reconnect] policy.
+#[derive(Debug)]
+pub enum ReconnectEvent<'a> {
+    /// A write failed because the connection broke.
+    Disconnected(&'a io::Error),
+    /// Reconnecting failed. The next attempt is made on the
+    /// first write after `retry_in`.
+    Failed {
+        attempt: u32,
+        error: &'a TelegrafError,
+        retry_in: Duration,
+    },
+    /// The connection was re-established.
+    Reconnected { attempts: u32 },
+}
+
+/// Opens a new connection, with the settings of the original one.
+pub(crate) type Connect = Arc<dyn Fn() -> Result<Connector, TelegrafError> + Send + Sync>;
+
+/// Reconnects a [Connector] following a [Reconnect] policy.
+pub(crate) struct Reconnector {
+    connect: Connect,
+    policy: Reconnect,
+    down: Option<Down>,
+}
+
+/// State of a broken connection.
+struct Down {
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+impl Reconnect {
+    /// Creates a policy backing off from 100ms up to 30s, with 20%
+    /// jitter, that doesn't retry failed writes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the delay after the first failed attempt.
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the longest delay between attempts.
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the factor the delay grows by after every failed attempt.
+    ///
+    /// Defaults to 2.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the fraction of each delay, between 0 and 1, that is
+    /// randomly taken off so that many clients don't reconnect to a
+    /// restarted Telegraf at once.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets whether a write that fails because the connection broke
+    /// is retried once, after reconnecting straight away. Data the
+    /// dead connection had accepted before the failure is still lost.
+    pub fn with_write_retry(mut self, retry: bool) -> Self {
+        self.retry_write = retry;
+        self
+    }
+
+    /// Sets a function called with every [ReconnectEvent],
+    /// e.g. to log them.
+    pub fn with_listener<F>(mut self, listener: F) -> Self
+    where
+        F: FnMut(&ReconnectEvent) + Send + 'static,
+    {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Delay after the given number of failed attempts.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let exp = self.multiplier.powi(attempts.saturating_sub(1) as i32);
+        let max = self.max_backoff.as_secs_f64();
+        let secs = (self.initial_backoff.as_secs_f64() * exp).min(max);
+        // Seconds near Duration::MAX round up past it as f64.
+        Duration::try_from_secs_f64(secs * (1.0 - self.jitter * random()))
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff))
+    }
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_write: false,
+            listener: None,
+        }
+    }
+}
+
+impl fmt::Display for ReconnectEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Disconnected(e) => write!(f, "connection lost: {}", e),
+            Self::Failed {
+                attempt,
+                error,
+                retry_in,
+            } => write!(
+                f,
+                "reconnect attempt {} failed: {}, retrying in {:?}",
+                attempt, error, retry_in
+            ),
+            Self::Reconnected { attempts } => {
+                write!(f, "reconnected after {} attempt(s)", attempts)
+            }
+        }
+    }
+}
+
+impl Reconnector {
+    pub(crate) fn new(connect: Connect, policy: Reconnect) -> Self {
+        Self {
+            connect,
+            policy,
+            down: None,
+        }
+    }
+
+    /// Writes to the connection, reconnecting it first if it
+    /// broke and the backoff has passed.
+    pub(crate) fn write(&mut self, conn: &mut Connector, buf: &[u8]) -> TelegrafResult {
+        if !conn.is_stream() {
+            return conn.write(buf);
+        }
+        if self.down.is_some() {
+            self.reconnect(conn)?;
+        }
+        match self.try_write(conn, buf) {
+            Err(_) if self.policy.retry_write && self.down.is_some() => {
+                self.reconnect(conn)?;
+                Ok(self.try_write(conn, buf)?)
+            }
+            r => Ok(r?),
+        }
+    }
+
+    /// Writes to the connection, noting if it broke.
+    fn try_write(&mut self, conn: &mut Connector, buf: &[u8]) -> io::Result<()> {
+        let res = conn.write_socket(buf);
+        if let Err(e) = &res {
+            if is_broken(e) {
+                self.emit(ReconnectEvent::Disconnected(e));
+                self.down = Some(Down {
+                    attempts: 0,
+                    next_attempt: Instant::now(),
+                });
+            }
+        }
+        res
+    }
+
+    fn reconnect(&mut self, conn: &mut Connector) -> TelegrafResult {
+        let now = Instant::now();
+        let down = match &mut self.down {
+            Some(d) if d.next_attempt > now => {
+                return Err(TelegrafError::ConnectionError(format!(
+                    "connection lost, reconnecting in {:?}",
+                    d.next_attempt - now
+                )))
+            }
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        down.attempts += 1;
+        let attempt = down.attempts;
+
+        match (self.connect)() {
+            Ok(c) => {
+                *conn = c;
+                self.down = None;
+                self.emit(ReconnectEvent::Reconnected { attempts: attempt });
+                Ok(())
+            }
+            Err(error) => {
+                let retry_in = self.policy.backoff(attempt);
+                // Delays past what an Instant holds never end in practice.
+                down.next_attempt = now
+                    .checked_add(retry_in)
+                    .unwrap_or_else(|| now + Duration::from_secs(100 * 365 * 24 * 3600));
+                self.emit(ReconnectEvent::Failed {
+                    attempt,
+                    error: &error,
+                    retry_in,
+                });
+                Err(error)
+            }
+        }
+    }
+
+    fn emit(&mut self, event: ReconnectEvent) {
+        if let Some(listener) = &mut self.policy.listener {
+            listener(&event);
+        }
+    }
+}
+
+/// Whether a write error means the peer is gone.
+fn is_broken(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::WriteZero
+    )
+}
+
+/// Random number in `[0, 1)`, from the randomly keyed std hasher.
+fn random() -> f64 {
+    let n = RandomState::new().build_hasher().finish();
+    (n >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, Client};
+    use std::{
+        io::Read,
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    /// Policy recording events as strings.
+    fn recording(policy: Reconnect) -> (Reconnect, Arc<Mutex<Vec<String>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let e = events.clone();
+        let policy = policy.with_listener(move |event: &ReconnectEvent| {
+            let s = match event {
+                ReconnectEvent::Disconnected(_) => "disconnected".to_owned(),
+                ReconnectEvent::Failed {
+                    attempt, retry_in, ..
+                } => format!("failed {} {:?}", attempt, retry_in),
+                ReconnectEvent::Reconnected { attempts } => format!("reconnected {}", attempts),
+            };
+            e.lock().unwrap().push(s);
+        });
+        (policy, events)
+    }
+
+    /// Writes until the dropped peer is noticed.
+    fn write_until_err(c: &mut Client) -> TelegrafError {
+        for _ in 0..100 {
+            if let Err(e) = c.write_point(&point!("m", ("f", 1))) {
+                return e;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("write never failed");
+    }
+
+    #[test]
+    fn can_reconnect_after_reset() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let (policy, events) = recording(Reconnect::new());
+        let mut c = Client::new(&url).unwrap().with_reconnect(policy);
+        drop(listener.accept().unwrap());
+
+        assert!(matches!(
+            write_until_err(&mut c),
+            TelegrafError::ConnectionError(_)
+        ));
+        c.write_point(&point!("m", ("f", 2))).unwrap();
+        c.close().unwrap();
+
+        let mut buf = String::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_string(&mut buf)
+            .unwrap();
+        assert_eq!(buf, "m f=2i\n");
+        assert_eq!(*events.lock().unwrap(), ["disconnected", "reconnected 1"]);
+    }
+
+    #[test]
+    fn can_retry_failed_write() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let (policy, events) = recording(Reconnect::new().with_write_retry(true));
+        let mut c = Client::new(&url).unwrap().with_reconnect(policy);
+        drop(listener.accept().unwrap());
+
+        for i in 0..100 {
+            c.write_point(&point!("m", ("f", i))).unwrap();
+            if events.lock().unwrap().len() == 2 {
+                c.close().unwrap();
+                let mut buf = String::new();
+                listener
+                    .accept()
+                    .unwrap()
+                    .0
+                    .read_to_string(&mut buf)
+                    .unwrap();
+                assert_eq!(buf, format!("m f={}i\n", i));
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("write never failed");
+    }
+
+    #[test]
+    fn backs_off_between_attempts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let policy = Reconnect::new()
+            .with_initial_backoff(Duration::from_secs(10))
+            .with_jitter(0.0);
+        let (policy, events) = recording(policy);
+        let mut c = Client::new(&url).unwrap().with_reconnect(policy);
+        drop(listener.accept().unwrap());
+        drop(listener);
+
+        write_until_err(&mut c);
+        assert!(c.write_point(&point!("m", ("f", 1))).is_err());
+        let res = c.write_point(&point!("m", ("f", 1)));
+        assert!(
+            matches!(res, Err(TelegrafError::ConnectionError(e)) if e.contains("reconnecting in"))
+        );
+        assert_eq!(*events.lock().unwrap(), ["disconnected", "failed 1 10s"]);
+    }
+
+    #[test]
+    fn can_compute_backoff() {
+        let policy = Reconnect::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(500))
+            .with_jitter(0.0);
+        let delays: Vec<_> = (1..=5).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+
+        let policy = policy.with_jitter(0.5);
+        for _ in 0..100 {
+            let d = policy.backoff(1);
+            assert!(d > Duration::from_millis(50) && d <= Duration::from_millis(100));
+        }
+
+        let policy = policy.with_max_backoff(Duration::MAX).with_jitter(0.0);
+        assert_eq!(policy.backoff(2000), Duration::MAX);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::{
+    protocol::{write_series_key, AsTag},
+    Point, PointRef,
+};
+
+/// Identity of a series: the measurement and tag set of a point.
+///
+/// Holds the escaped line protocol form, e.g. `cpu,host=a,region=b`,
+/// with tags sorted by key. Points of the same series have equal
+/// keys regardless of the order their tags were added in, so
+/// series keys can be used to group points in maps.
+///
+/// Created via [crate::Point::series_key].
+///
+/// # Examples
+///
+/// ```
+/// use telegraf::point;
+///
+/// let a = point!("cpu", ("region", "b")("host", "a"), ("load", 0.5));
+/// let b = point!("cpu", ("host", "a")("region", "b"), ("load", 0.7));
+/// assert_eq!(a.series_key(), b.series_key());
+/// assert_eq!(a.series_key().as_str(), "cpu,host=a,region=b");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SeriesKey(String);
+
+impl SeriesKey {
+    fn new<T: AsTag>(measurement: &str, tags: &[T]) -> Self {
+        let mut key = String::new();
+        // Writing to a String cannot fail.
+        let _ = write_series_key(&mut key, measurement, tags, false, &mut Vec::new());
+        Self(key)
+    }
+
+    /// The escaped series key.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Point {
+    /// Returns the key of the series this point belongs to.
+    pub fn series_key(&self) -> SeriesKey {
+        SeriesKey::new(&self.measurement, &self.tags)
+    }
+}
+
+impl PointRef<'_> {
+    /// Returns the key of the series this point belongs to.
+    pub fn series_key(&self) -> SeriesKey {
+        SeriesKey::new(&self.measurement, &self.tags)
+    }
+}
+
+impl fmt::Display for SeriesKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<SeriesKey> for String {
+    fn from(k: SeriesKey) -> Self {
+        k.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{point, point_ref};
+
+    #[test]
+    fn can_create_series_key() {
+        let p = point!("m 1,x", ("b", "2")("a=", "1 ,"), ("f", 1));
+        assert_eq!(p.series_key().as_str(), r"m\ 1\,x,a\==1\ \,,b=2");
+        assert_eq!(point!("m", ("f", 1)).series_key().as_str(), "m");
+        assert_eq!(
+            point_ref!("m", ("b", "2")("a", "1"), ("f", 1)).series_key(),
+            point!("m", ("a", "1")("b", "2"), ("g", 2)).series_key()
+        );
+    }
+
+    #[test]
+    fn orders_by_key() {
+        let a = point!("m", ("t", "a"), ("f", 1)).series_key();
+        let b = point!("m", ("t", "b"), ("f", 1)).series_key();
+        let c = point!("n", ("f", 1)).series_key();
+        assert!(a < b && b < c);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn can_group_by_series() {
+        let pts = vec![
+            point!("m", ("t", "a"), ("f", 1)),
+            point!("m", ("t", "b"), ("f", 2)),
+            point!("m", ("t", "a"), ("f", 3)),
+        ];
+        let mut sums: HashMap<SeriesKey, usize> = HashMap::new();
+        for p in &pts {
+            *sums.entry(p.series_key()).or_default() += 1;
+        }
+        assert_eq!(sums[&pts[0].series_key()], 2);
+        assert_eq!(sums[&pts[1].series_key()], 1);
+    }
+}
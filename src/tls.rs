@@ -0,0 +1,440 @@
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{self, Read, Write},
+    net::{Shutdown, TcpStream},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+
+use crate::TelegrafError;
+
+/// TLS settings for `tls://` and `https://` connections,
+/// used with [crate::Client::new_with_tls].
+///
+/// Servers are verified against the Mozilla root certificates
+/// unless CA certificates are given. Files are read and certificates
+/// parsed once, when the client is created, and reused when it
+/// reconnects.
+///
+/// # Examples
+///
+/// ```no_run
+/// use telegraf::{Client, TlsConfig};
+///
+/// let tls = TlsConfig::new()
+///     .with_ca_file("/etc/telegraf/ca.pem")
+///     .with_client_cert_files("/etc/app/cert.pem", "/etc/app/key.pem");
+/// let client = Client::new_with_tls("tls://telegraf.internal:8094", tls).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    ca: Vec<Pem>,
+    server_name: Option<String>,
+    client_cert: Option<(Pem, Pem)>,
+}
+
+/// PEM data, or a file to read it from.
+#[derive(Debug, Clone)]
+enum Pem {
+    Data(Vec<u8>),
+    File(PathBuf),
+}
+
+/// Client side of a TLS connection.
+///
+/// The stream sits behind a mutex so [TlsStream::close] can send
+/// `close_notify` from the shared reference `Client::close` takes.
+/// Reads and writes go through `&mut` and never lock.
+pub(crate) struct TlsStream {
+    stream: Mutex<StreamOwned<ClientConnection, TcpStream>>,
+    /// Handle to the socket for shutting it down.
+    sock: TcpStream,
+}
+
+/// Client side of TLS connections to one host.
+#[derive(Clone)]
+pub(crate) struct TlsConnector {
+    config: Arc<ClientConfig>,
+    server_name: ServerName<'static>,
+}
+
+impl TlsConfig {
+    /// Creates settings verifying servers against the Mozilla
+    /// root certificates, without a client certificate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the CA certificates in the given PEM data, like
+    /// `tls_allowed_cacerts` of Telegraf. Once any are given, the
+    /// Mozilla roots are no longer trusted.
+    pub fn with_ca_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca.push(Pem::Data(pem.into()));
+        self
+    }
+
+    /// Trusts the CA certificates in the given PEM file.
+    pub fn with_ca_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca.push(Pem::File(path.into()));
+        self
+    }
+
+    /// Sets the name the server certificate is verified for.
+    ///
+    /// Defaults to the host of the connection URL.
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Authenticates with the certificate chain and private key in
+    /// the given PEM data, for listeners requiring mutual TLS.
+    pub fn with_client_cert_pem(
+        mut self,
+        cert: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.client_cert = Some((Pem::Data(cert.into()), Pem::Data(key.into())));
+        self
+    }
+
+    /// Authenticates with the certificate chain and private
+    /// key in the given PEM files.
+    pub fn with_client_cert_files(
+        mut self,
+        cert: impl Into<PathBuf>,
+        key: impl Into<PathBuf>,
+    ) -> Self {
+        self.client_cert = Some((Pem::File(cert.into()), Pem::File(key.into())));
+        self
+    }
+}
+
+impl Pem {
+    fn read(&self) -> Result<Vec<u8>, TelegrafError> {
+        match self {
+            Pem::Data(d) => Ok(d.clone()),
+            Pem::File(p) => fs::read(p).map_err(|e| {
+                TelegrafError::ConnectionError(format!("cannot read {}: {}", p.display(), e))
+            }),
+        }
+    }
+
+    fn certs(&self) -> Result<Vec<CertificateDer<'static>>, TelegrafError> {
+        let certs = CertificateDer::pem_slice_iter(&self.read()?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| tls_error("invalid certificate", e))?;
+        if certs.is_empty() {
+            return Err(TelegrafError::ConnectionError(
+                "no certificate found in PEM data".to_owned(),
+            ));
+        }
+        Ok(certs)
+    }
+}
+
+impl TlsConnector {
+    /// Builds the connector for a host, reading all
+    /// certificates and keys.
+    pub(crate) fn new(cfg: &TlsConfig, host: &str) -> Result<Self, TelegrafError> {
+        let mut roots = RootCertStore::empty();
+        if cfg.ca.is_empty() {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for pem in &cfg.ca {
+            for cert in pem.certs()? {
+                roots
+                    .add(cert)
+                    .map_err(|e| tls_error("invalid CA certificate", e))?;
+            }
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error("invalid TLS configuration", e))?
+            .with_root_certificates(roots);
+        let config = match &cfg.client_cert {
+            Some((cert, key)) => {
+                let key = PrivateKeyDer::from_pem_slice(&key.read()?)
+                    .map_err(|e| tls_error("invalid private key", e))?;
+                builder
+                    .with_client_auth_cert(cert.certs()?, key)
+                    .map_err(|e| tls_error("invalid client certificate", e))?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let name = cfg.server_name.as_deref().unwrap_or(host);
+        // IPv6 hosts of URLs are bracketed.
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        let server_name = ServerName::try_from(name.to_owned())
+            .map_err(|e| tls_error("invalid server name", e))?;
+        Ok(Self {
+            config: Arc::new(config),
+            server_name,
+        })
+    }
+
+    /// Performs the handshake on a connected socket.
+    pub(crate) fn connect(&self, sock: TcpStream) -> io::Result<TlsStream> {
+        let conn = ClientConnection::new(self.config.clone(), self.server_name.clone())
+            .map_err(io::Error::other)?;
+        let handle = sock.try_clone()?;
+        let mut stream = StreamOwned::new(conn, sock);
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+        Ok(TlsStream {
+            stream: Mutex::new(stream),
+            sock: handle,
+        })
+    }
+}
+
+impl TlsStream {
+    pub(crate) fn sock(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    /// Sends `close_notify`, so the server can tell the stream
+    /// was not truncated, and shuts down the socket.
+    pub(crate) fn close(&self) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        stream.conn.send_close_notify();
+        let flushed = stream.flush();
+        self.sock.shutdown(Shutdown::Both)?;
+        flushed
+    }
+
+    fn stream(&mut self) -> &mut StreamOwned<ClientConnection, TcpStream> {
+        self.stream
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream().flush()
+    }
+}
+
+fn tls_error(what: &str, e: impl std::fmt::Display) -> TelegrafError {
+    TelegrafError::ConnectionError(format!("{}: {}", what, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, Client, Reconnect};
+    use rustls::{server::WebPkiClientVerifier, ServerConfig, ServerConnection};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    const CA: &[u8] = include_bytes!("../testdata/tls/ca.pem");
+    const SERVER_CERT: &[u8] = include_bytes!("../testdata/tls/server.pem");
+    const SERVER_KEY: &[u8] = include_bytes!("../testdata/tls/server-key.pem");
+    const CLIENT_CERT: &[u8] = include_bytes!("../testdata/tls/client.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("../testdata/tls/client-key.pem");
+
+    /// Server settings, requiring a client certificate if `mtls` is set.
+    fn server_config(mtls: bool) -> Arc<ServerConfig> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = if mtls {
+            let mut roots = RootCertStore::empty();
+            roots.add_parsable_certificates(CertificateDer::pem_slice_iter(CA).map(Result::unwrap));
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        let config = builder
+            .with_single_cert(
+                CertificateDer::pem_slice_iter(SERVER_CERT)
+                    .map(Result::unwrap)
+                    .collect(),
+                PrivateKeyDer::from_pem_slice(SERVER_KEY).unwrap(),
+            )
+            .unwrap();
+        Arc::new(config)
+    }
+
+    /// Accepts one TLS connection, requiring a client
+    /// certificate if `mtls` is set.
+    fn listen<F, T>(mtls: bool, handle: F) -> (u16, thread::JoinHandle<T>)
+    where
+        F: FnOnce(StreamOwned<ServerConnection, TcpStream>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let config = server_config(mtls);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let conn = ServerConnection::new(config).unwrap();
+            handle(StreamOwned::new(conn, sock))
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn can_write_with_client_cert() {
+        let (port, server) = listen(true, |mut s| {
+            let mut buf = [0; 7];
+            s.read_exact(&mut buf).unwrap();
+            buf
+        });
+        let tls = TlsConfig::new()
+            .with_ca_pem(CA)
+            .with_client_cert_pem(CLIENT_CERT, CLIENT_KEY);
+        let url = format!("tls://localhost:{}", port);
+        let mut c = Client::new_with_tls(&url, tls).unwrap();
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+
+        assert_eq!(&server.join().unwrap(), b"m f=1i\n");
+    }
+
+    #[test]
+    fn sends_close_notify() {
+        let (port, server) = listen(false, |mut s| {
+            let mut buf = Vec::new();
+            s.read_to_end(&mut buf).unwrap();
+            buf
+        });
+        let url = format!("tls://localhost:{}", port);
+        let mut c = Client::new_with_tls(&url, TlsConfig::new().with_ca_pem(CA)).unwrap();
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        c.close().unwrap();
+
+        assert_eq!(server.join().unwrap(), b"m f=1i\n");
+    }
+
+    #[test]
+    fn can_reconnect_with_client_cert() {
+        let config = server_config(true);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let mut conn = ServerConnection::new(config.clone()).unwrap();
+            while conn.is_handshaking() {
+                conn.complete_io(&mut sock).unwrap();
+            }
+            drop((conn, sock));
+
+            let (sock, _) = listener.accept().unwrap();
+            let mut s = StreamOwned::new(ServerConnection::new(config).unwrap(), sock);
+            let mut buf = [0; 7];
+            s.read_exact(&mut buf).unwrap();
+            buf
+        });
+        // Files are only read when the client is created.
+        let dir = std::env::temp_dir().join(format!("telegraf-tls-{}", port));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cert.pem"), CLIENT_CERT).unwrap();
+        fs::write(dir.join("key.pem"), CLIENT_KEY).unwrap();
+        let tls = TlsConfig::new()
+            .with_ca_pem(CA)
+            .with_client_cert_files(dir.join("cert.pem"), dir.join("key.pem"));
+        let url = format!("tls://localhost:{}", port);
+        let mut c = Client::new_with_tls(&url, tls)
+            .unwrap()
+            .with_reconnect(Reconnect::new().with_write_retry(true));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut broke = false;
+        while !server.is_finished() {
+            broke |= c.write_point(&point!("m", ("f", 1))).is_err();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(&server.join().unwrap(), b"m f=1i\n");
+        assert!(!broke);
+    }
+
+    #[test]
+    fn can_override_server_name() {
+        let (port, server) = listen(false, |mut s| {
+            let mut buf = [0; 7];
+            s.read_exact(&mut buf).unwrap();
+        });
+        let tls = TlsConfig::new()
+            .with_ca_pem(CA)
+            .with_server_name("localhost");
+        let url = format!("tls://127.0.0.1:{}", port);
+        let mut c = Client::new_with_tls(&url, tls).unwrap();
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_server() {
+        let (port, server) = listen(false, |mut s| {
+            let _ = s.read(&mut [0; 1]);
+        });
+        let url = format!("tls://localhost:{}", port);
+        let res = Client::new_with_tls(&url, TlsConfig::new());
+        assert!(matches!(res, Err(TelegrafError::ConnectionError(_))));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_pem() {
+        let tls = TlsConfig::new().with_ca_pem("not a certificate");
+        let res = Client::new_with_tls("tls://localhost:1", tls);
+        assert!(matches!(res, Err(TelegrafError::ConnectionError(_))));
+    }
+
+    #[test]
+    fn can_write_over_https() {
+        let (port, server) = listen(false, |s| {
+            let mut r = BufReader::new(s);
+            let mut line = String::new();
+            r.read_line(&mut line).unwrap();
+            let mut header = String::new();
+            while header != "\r\n" {
+                header.clear();
+                r.read_line(&mut header).unwrap();
+            }
+            let mut body = [0; 7];
+            r.read_exact(&mut body).unwrap();
+            r.get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            r.get_mut().flush().unwrap();
+            (line, body)
+        });
+        let url = format!("https://localhost:{}/?db=metrics", port);
+        let mut c = Client::new_with_tls(&url, TlsConfig::new().with_ca_pem(CA)).unwrap();
+        c.write_point(&point!("m", ("f", 1))).unwrap();
+
+        let (line, body) = server.join().unwrap();
+        assert_eq!(line, "POST /write?db=metrics HTTP/1.1\r\n");
+        assert_eq!(&body, b"m f=1i\n");
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+
+use crate::{
+    protocol::{AsField, AsTag, FieldDataRef},
+    Point,
+};
+
+/// Largest string field value accepted by InfluxDB, in bytes.
+pub const MAX_STRING_FIELD_LEN: usize = 64 * 1024;
+
+/// Problem with a [crate::Point] found by [crate::Point::validate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The measurement name is empty.
+    EmptyMeasurement,
+    /// The point has no fields.
+    NoFields,
+    /// A tag has an empty key.
+    EmptyTagKey,
+    /// The tag with the given key has an empty value.
+    EmptyTagValue(String),
+    /// A field has an empty key.
+    EmptyFieldKey,
+    /// The tag key is used more than once.
+    DuplicateTag(String),
+    /// The field key is used more than once.
+    DuplicateField(String),
+    /// The key is used by both a tag and a field.
+    TagFieldCollision(String),
+    /// A tag or field uses the reserved `time` key.
+    ReservedKey(String),
+    /// The float field is NaN or infinite.
+    NonFiniteFloat(String),
+    /// The string field is longer than [MAX_STRING_FIELD_LEN].
+    StringTooLong(String),
+}
+
+impl Point {
+    /// Checks the point against the rules of the Influx data
+    /// model, returning every problem found.
+    ///
+    /// Points are validated automatically when written by
+    /// a [crate::Client].
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        validate_parts(&self.measurement, &self.tags, &self.fields)
+    }
+}
+
+/// Validates the parts of a point in either owned or borrowed form.
+/// Duplicate keys are found by comparing pairs rather than hashing,
+/// as points have few keys and this avoids allocating.
+pub(crate) fn validate_parts<T: AsTag, F: AsField>(
+    measurement: &str,
+    tags: &[T],
+    fields: &[F],
+) -> Result<(), Vec<ValidationError>> {
+    use ValidationError::*;
+
+    let mut errs = Vec::new();
+    if measurement.is_empty() {
+        errs.push(EmptyMeasurement);
+    }
+    if fields.is_empty() {
+        errs.push(NoFields);
+    }
+
+    for (i, t) in tags.iter().enumerate() {
+        if t.name().is_empty() {
+            errs.push(EmptyTagKey);
+            continue;
+        }
+        if t.value().is_empty() {
+            errs.push(EmptyTagValue(t.name().to_owned()));
+        }
+        if t.name() == "time" {
+            errs.push(ReservedKey(t.name().to_owned()));
+        }
+        if tags[..i].iter().any(|o| o.name() == t.name()) {
+            errs.push(DuplicateTag(t.name().to_owned()));
+        }
+    }
+
+    for (i, f) in fields.iter().enumerate() {
+        if f.name().is_empty() {
+            errs.push(EmptyFieldKey);
+            continue;
+        }
+        if f.name() == "time" {
+            errs.push(ReservedKey(f.name().to_owned()));
+        }
+        if fields[..i].iter().any(|o| o.name() == f.name()) {
+            errs.push(DuplicateField(f.name().to_owned()));
+        } else if tags.iter().any(|t| t.name() == f.name()) {
+            errs.push(TagFieldCollision(f.name().to_owned()));
+        }
+        match f.value() {
+            FieldDataRef::Float(v) if !v.is_finite() => {
+                errs.push(NonFiniteFloat(f.name().to_owned()));
+            }
+            FieldDataRef::Str(s) if s.len() > MAX_STRING_FIELD_LEN => {
+                errs.push(StringTooLong(f.name().to_owned()));
+            }
+            _ => {}
+        }
+    }
+
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(errs)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationError::*;
+
+        match self {
+            EmptyMeasurement => write!(f, "measurement is empty"),
+            NoFields => write!(f, "points must have at least 1 field"),
+            EmptyTagKey => write!(f, "tag key is empty"),
+            EmptyTagValue(k) => write!(f, "tag {:?} has an empty value", k),
+            EmptyFieldKey => write!(f, "field key is empty"),
+            DuplicateTag(k) => write!(f, "tag {:?} is set more than once", k),
+            DuplicateField(k) => write!(f, "field {:?} is set more than once", k),
+            TagFieldCollision(k) => write!(f, "{:?} is both a tag and a field", k),
+            ReservedKey(k) => write!(f, "{:?} is a reserved key", k),
+            NonFiniteFloat(k) => write!(f, "field {:?} is not a finite float", k),
+            StringTooLong(k) => write!(
+                f,
+                "field {:?} is longer than {} bytes",
+                k, MAX_STRING_FIELD_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point;
+    use ValidationError::*;
+
+    #[test]
+    fn can_validate_point() {
+        let p = point!(
+            "m",
+            ("t", "v"),
+            ("f", 1)("s", "x".repeat(MAX_STRING_FIELD_LEN))
+        );
+        assert_eq!(p.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let p = point!(
+            "",
+            ("t", "")("t", "v")("", "v")("time", "v")("c", "v"),
+            ("f", f64::NAN)("f", 1)("c", 1)("", 1)("i", f64::INFINITY)(
+                "s",
+                "x".repeat(MAX_STRING_FIELD_LEN + 1)
+            )
+        );
+        assert_eq!(
+            p.validate(),
+            Err(vec![
+                EmptyMeasurement,
+                EmptyTagValue("t".to_owned()),
+                DuplicateTag("t".to_owned()),
+                EmptyTagKey,
+                ReservedKey("time".to_owned()),
+                NonFiniteFloat("f".to_owned()),
+                DuplicateField("f".to_owned()),
+                TagFieldCollision("c".to_owned()),
+                EmptyFieldKey,
+                NonFiniteFloat("i".to_owned()),
+                StringTooLong("s".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let p = Point::new("m".to_owned(), vec![], vec![], None);
+        assert_eq!(p.validate(), Err(vec![NoFields]));
+    }
+
+    #[test]
+    fn reports_reserved_field_key() {
+        let p = point!("m", ("time", 1));
+        assert_eq!(p.validate(), Err(vec![ReservedKey("time".to_owned())]));
+    }
+}
@@ -2,8 +2,8 @@ use proc_macro::TokenStream;
 use proc_macro2::{TokenStream as TStream2, TokenTree};
 use quote::quote;
 use syn::{
-    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Generics,
-    Path, Type,
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, GenericArgument,
+    GenericParam, Generics, Path, PathArguments, Type,
 };
 
 fn krate() -> TStream2 {
@@ -25,16 +25,35 @@ fn expand_metric(tokens: TokenStream) -> TokenStream {
     let generics = add_trait_bounds(input.generics);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let pt = get_to_point(&input.data);
+    let (pt_ref, with_pt_ref) = get_to_point_ref(&input.data, &measurement);
 
     let expanded = quote! {
         impl #impl_generics #krate::Metric for #name #ty_generics #where_clause {
             fn to_point(&self) -> #krate::Point {
                 let mut pf: Vec<(String, Box<dyn #krate::IntoFieldData>)> = Vec::new();
                 let mut pt: Vec<(String, String)> = Vec::new();
-                let mut tsp: Option<u64> = None;
+                let mut tsp: Option<Result<#krate::Timestamp, #krate::TelegrafError>> = None;
                 #pt
-                #krate::Point::new(#measurement, pt, pf, tsp)
+                let mut point = #krate::Point::new(#measurement.to_string(), pt, pf, None);
+                point.timestamp = tsp.and_then(Result::ok);
+                point
             }
+
+            fn try_to_point(&self) -> Result<#krate::Point, #krate::TelegrafError> {
+                let mut pf: Vec<(String, Box<dyn #krate::IntoFieldData>)> = Vec::new();
+                let mut pt: Vec<(String, String)> = Vec::new();
+                let mut tsp: Option<Result<#krate::Timestamp, #krate::TelegrafError>> = None;
+                #pt
+                let mut point = #krate::Point::new(#measurement.to_string(), pt, pf, None);
+                point.timestamp = tsp.transpose()?;
+                Ok(point)
+            }
+
+            fn to_point_ref(&self) -> Result<#krate::PointRef<'_>, #krate::TelegrafError> {
+                #pt_ref
+            }
+
+            #with_pt_ref
         }
     };
 
@@ -60,9 +79,9 @@ fn get_measurement_name(input: &DeriveInput) -> TStream2 {
                     _ => panic!("unexpected type"),
                 })
                 .unwrap();
-            quote!(#q.to_string())
+            quote!(#q)
         }
-        None => quote!(stringify!(#default).to_string()),
+        None => quote!(stringify!(#default)),
     }
 }
 
@@ -127,13 +146,89 @@ fn is_timestamp(attr: &Attribute) -> bool {
         .unwrap()
 }
 
-fn get_to_point(data: &Data) -> TStream2 {
-    fn path_is_option(path: &Path) -> bool {
-        path.leading_colon.is_none()
-            && path.segments.len() == 1
-            && path.segments.iter().next().unwrap().ident == "Option"
+/// Reads the optional `precision = "<unit>"` argument of a
+/// timestamp attribute, defaulting to nanoseconds.
+fn timestamp_precision(attrs: &[Attribute]) -> TStream2 {
+    let krate = krate();
+    let unit = attrs
+        .iter()
+        .filter(|a| is_timestamp(a))
+        .flat_map(|a| a.tokens.clone())
+        .filter_map(|t_tree| match t_tree {
+            TokenTree::Group(group) => Some(group.stream().into_iter().collect::<Vec<_>>()),
+            _ => None,
+        })
+        .find_map(|tokens| {
+            tokens.windows(3).find_map(|w| match w {
+                [TokenTree::Ident(i), TokenTree::Punct(p), TokenTree::Literal(l)]
+                    if i == "precision" && p.as_char() == '=' =>
+                {
+                    Some(l.to_string())
+                }
+                _ => None,
+            })
+        });
+
+    match unit.as_deref() {
+        None | Some("\"ns\"") => quote!(#krate::Precision::Nanoseconds),
+        Some("\"us\"") => quote!(#krate::Precision::Microseconds),
+        Some("\"ms\"") => quote!(#krate::Precision::Milliseconds),
+        Some("\"s\"") => quote!(#krate::Precision::Seconds),
+        Some(other) => panic!("unknown timestamp precision {}", other),
+    }
+}
+
+/// Converts a reference to a timestamp member, through its
+/// `IntoTimestamp` impl or else `Into<u64>`.
+fn member_timestamp(value: TStream2, precision: TStream2) -> TStream2 {
+    let krate = krate();
+    quote!({
+        use #krate::derive_support::{ViaIntoTimestamp as _, ViaIntoU64 as _};
+        (&&#krate::derive_support::TimestampMember(#value)).member_timestamp(#precision)
+    })
+}
+
+fn path_is_option(path: &Path) -> bool {
+    path.leading_colon.is_none()
+        && path.segments.len() == 1
+        && path.segments.iter().next().unwrap().ident == "Option"
+}
+
+/// Returns `T` for a field of type `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(typath) if typath.qself.is_none() && path_is_option(&typath.path) => {
+            match &typath.path.segments[0].arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|a| match a {
+                    GenericArgument::Type(t) => Some(t),
+                    _ => None,
+                }),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether a type is `String` or `&str`, which tags can borrow
+/// rather than format.
+fn is_str_like(ty: &Type) -> bool {
+    match ty {
+        Type::Path(typath) => {
+            typath.qself.is_none()
+                && typath
+                    .path
+                    .segments
+                    .last()
+                    .map(|seg| seg.ident == "String")
+                    .unwrap_or_default()
+        }
+        Type::Reference(r) => matches!(&*r.elem, Type::Path(p) if p.path.is_ident("str")),
+        _ => false,
     }
+}
 
+fn get_to_point(data: &Data) -> TStream2 {
     match *data {
         Data::Struct(ref data) => {
             match data.fields {
@@ -151,9 +246,10 @@ fn get_to_point(data: &Data) -> TStream2 {
                                             }
                                         )
                                     } else if f.attrs.iter().any(is_timestamp) {
+                                        let ts = member_timestamp(quote!(v), timestamp_precision(&f.attrs));
                                         quote!(
                                             if let Some(ref v) = self.#name {
-                                                tsp = tsp.or(Some(v.into()));
+                                                tsp = tsp.or(Some(#ts));
                                             }
                                         )
                                     } else {
@@ -169,7 +265,8 @@ fn get_to_point(data: &Data) -> TStream2 {
                                     if f.attrs.iter().any(is_tag) {
                                         quote!(pt.push((stringify!(#name).to_string(), format!("{}", self.#name)));)
                                     } else if f.attrs.iter().any(is_timestamp) {
-                                        quote!(tsp = tsp.or(Some(self.#name.into()));)
+                                        let ts = member_timestamp(quote!(&self.#name), timestamp_precision(&f.attrs));
+                                        quote!(tsp = tsp.or(Some(#ts));)
                                     } else {
                                         quote!(pf.push((stringify!(#name).to_string(), Box::new(self.#name.clone())));)
                                     }
@@ -184,3 +281,126 @@ fn get_to_point(data: &Data) -> TStream2 {
         _ => panic!("cannot derive for data type")
     }
 }
+
+/// Builds the body of `to_point_ref`, which borrows string tags,
+/// field names and string fields instead of copying them, and
+/// for structs without `Option` members a `with_point_ref` that
+/// keeps the tags and fields in arrays on the stack.
+fn get_to_point_ref(data: &Data, measurement: &TStream2) -> (TStream2, TStream2) {
+    let krate = krate();
+    let cow = quote!(::std::borrow::Cow);
+
+    let fields = match *data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => panic!("only named fields supported"),
+        },
+        _ => panic!("cannot derive for data type"),
+    };
+
+    let mut tags = Vec::new();
+    let mut values = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut has_optionals = false;
+    for f in fields {
+        let name = &f.ident;
+        let inner = option_inner(&f.ty);
+        let ty = inner.unwrap_or(&f.ty);
+        has_optionals |= inner.is_some();
+        let v = if inner.is_some() {
+            quote!(v)
+        } else {
+            quote!((&self.#name))
+        };
+        let part = if f.attrs.iter().any(is_tag) {
+            let value = if is_str_like(ty) {
+                quote!(#cow::Borrowed(::std::convert::AsRef::<str>::as_ref(#v)))
+            } else {
+                quote!(#cow::Owned(format!("{}", #v)))
+            };
+            quote!(#krate::protocol::TagRef {
+                name: #cow::Borrowed(stringify!(#name)),
+                value: #value,
+            })
+        } else if f.attrs.iter().any(is_timestamp) {
+            member_timestamp(v, timestamp_precision(&f.attrs))
+        } else {
+            quote!(#krate::protocol::FieldRef {
+                name: #cow::Borrowed(stringify!(#name)),
+                value: #krate::IntoFieldData::field_data_ref(#v),
+            })
+        };
+        let parts = if f.attrs.iter().any(is_tag) {
+            &mut tags
+        } else if f.attrs.iter().any(is_timestamp) {
+            &mut timestamps
+        } else {
+            &mut values
+        };
+        parts.push((name, inner.is_some(), part));
+    }
+
+    let pushes =
+        |parts: &[(_, bool, TStream2)], push: &dyn Fn(&TStream2) -> TStream2| -> TStream2 {
+            parts
+                .iter()
+                .map(|(name, optional, part)| {
+                    let push = push(part);
+                    if *optional {
+                        quote!(if let Some(ref v) = self.#name { #push })
+                    } else {
+                        push
+                    }
+                })
+                .collect()
+        };
+    let tag_pushes = pushes(&tags, &|part| quote!(tags.push(#part);));
+    let field_pushes = pushes(&values, &|part| quote!(fields.push(#part);));
+    let ts_pushes = pushes(&timestamps, &|part| quote!(tsp = tsp.or(Some(#part));));
+    let tag_count = tags.len();
+    let field_count = values.len();
+
+    let to_point_ref = quote! {
+        #[allow(unused_mut)]
+        let mut tags = Vec::with_capacity(#tag_count);
+        #[allow(unused_mut)]
+        let mut fields = Vec::with_capacity(#field_count);
+        #[allow(unused_mut)]
+        let mut tsp: Option<Result<#krate::Timestamp, #krate::TelegrafError>> = None;
+        #tag_pushes
+        #field_pushes
+        #ts_pushes
+        Ok(#krate::PointRef {
+            measurement: #cow::Borrowed(#measurement),
+            tags: #cow::Owned(tags),
+            fields: #cow::Owned(fields),
+            timestamp: tsp.transpose()?,
+        })
+    };
+
+    if has_optionals {
+        return (to_point_ref, TStream2::new());
+    }
+
+    let tag_parts = tags.iter().map(|(_, _, part)| part);
+    let field_parts = values.iter().map(|(_, _, part)| part);
+    let with_point_ref = quote! {
+        fn with_point_ref(
+            &self,
+            f: &mut dyn FnMut(&#krate::PointRef<'_>) -> Result<(), #krate::TelegrafError>,
+        ) -> Result<(), #krate::TelegrafError> {
+            let tags: [#krate::protocol::TagRef<'_>; #tag_count] = [#(#tag_parts),*];
+            let fields: [#krate::protocol::FieldRef<'_>; #field_count] = [#(#field_parts),*];
+            #[allow(unused_mut)]
+            let mut tsp: Option<Result<#krate::Timestamp, #krate::TelegrafError>> = None;
+            #ts_pushes
+            f(&#krate::PointRef {
+                measurement: #cow::Borrowed(#measurement),
+                tags: #cow::Borrowed(&tags),
+                fields: #cow::Borrowed(&fields),
+                timestamp: tsp.transpose()?,
+            })
+        }
+    };
+    (to_point_ref, with_point_ref)
+}
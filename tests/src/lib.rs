@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use telegraf::*;
 
 #[derive(Metric)]
@@ -47,9 +49,64 @@ struct Timestamp {
     i: f32,
 }
 
+#[derive(Metric)]
+struct TimestampPrecision {
+    #[telegraf(timestamp, precision = "ms")]
+    ts: u64,
+    i: f32,
+}
+
+#[derive(Metric)]
+struct OptionalTimestamp {
+    #[telegraf(timestamp)]
+    ts: Option<u64>,
+    i: f32,
+}
+
+#[derive(Clone, Copy)]
+struct Ticks(u16);
+
+impl From<Ticks> for u64 {
+    fn from(t: Ticks) -> u64 {
+        t.0 as u64 * 10
+    }
+}
+
+#[derive(Metric)]
+struct CustomTimestamp {
+    #[telegraf(timestamp)]
+    ts: Option<Ticks>,
+    i: f32,
+}
+
+#[derive(Metric)]
+struct SystemTimestamp {
+    #[telegraf(timestamp, precision = "s")]
+    ts: std::time::SystemTime,
+    i: f32,
+}
+
+#[derive(Metric)]
+struct ChronoTimestamp {
+    #[telegraf(timestamp, precision = "ms")]
+    ts: chrono::DateTime<chrono::Utc>,
+    i: f32,
+}
+
+#[derive(Metric)]
+struct TimeTimestamp {
+    #[telegraf(timestamp)]
+    ts: Option<time::OffsetDateTime>,
+    i: f32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{
+        borrow::Cow,
+        time::{Duration, UNIX_EPOCH},
+    };
 
     #[test]
     fn can_derive_string_fields() {
@@ -114,4 +171,165 @@ mod tests {
         let exp = point!("Timestamp", ("i", 1.); 10);
         assert_eq!(s.to_point(), exp);
     }
+
+    #[test]
+    fn can_derive_with_into_u64_timestamp() {
+        let s = CustomTimestamp {
+            ts: Some(Ticks(2)),
+            i: 1.,
+        };
+        let exp = point!("CustomTimestamp", ("i", 1.); 20);
+        assert_eq!(s.to_point(), exp);
+        assert_eq!(s.to_point_ref().unwrap().to_point(), exp);
+    }
+
+    #[test]
+    fn can_derive_with_timestamp_precision() {
+        let s = TimestampPrecision { ts: 10, i: 1. };
+        let exp = point!("TimestampPrecision", ("i", 1.); telegraf::Timestamp::new(10, Precision::Milliseconds));
+        assert_eq!(s.to_point(), exp);
+    }
+
+    #[test]
+    fn can_derive_with_optional_timestamp() {
+        let s = OptionalTimestamp {
+            ts: Some(10),
+            i: 1.,
+        };
+        let exp = point!("OptionalTimestamp", ("i", 1.); 10);
+        assert_eq!(s.to_point(), exp);
+
+        let s = OptionalTimestamp { ts: None, i: 1. };
+        let exp = point!("OptionalTimestamp", ("i", 1.));
+        assert_eq!(s.to_point(), exp);
+    }
+
+    #[test]
+    fn can_derive_with_system_time() {
+        let s = SystemTimestamp {
+            ts: UNIX_EPOCH + Duration::from_millis(10_500),
+            i: 1.,
+        };
+        let exp =
+            point!("SystemTimestamp", ("i", 1.); telegraf::Timestamp::new(10, Precision::Seconds));
+        assert_eq!(s.to_point(), exp);
+        assert_eq!(s.try_to_point().unwrap(), exp);
+    }
+
+    #[test]
+    fn can_derive_with_chrono() {
+        let s = ChronoTimestamp {
+            ts: chrono::DateTime::from_timestamp(10, 5_000_000).unwrap(),
+            i: 1.,
+        };
+        let exp = point!("ChronoTimestamp", ("i", 1.); telegraf::Timestamp::new(10_005, Precision::Milliseconds));
+        assert_eq!(s.to_point(), exp);
+    }
+
+    #[test]
+    fn can_derive_with_time() {
+        let s = TimeTimestamp {
+            ts: Some(time::OffsetDateTime::from_unix_timestamp(10).unwrap()),
+            i: 1.,
+        };
+        let exp = point!("TimeTimestamp", ("i", 1.); 10_000_000_000);
+        assert_eq!(s.to_point(), exp);
+    }
+
+    #[test]
+    fn cannot_derive_with_pre_epoch_time() {
+        let s = SystemTimestamp {
+            ts: UNIX_EPOCH - Duration::from_secs(1),
+            i: 1.,
+        };
+        assert!(s.try_to_point().is_err());
+        assert_eq!(s.to_point(), point!("SystemTimestamp", ("i", 1.)));
+
+        let s = ChronoTimestamp {
+            ts: chrono::DateTime::from_timestamp(-10, 0).unwrap(),
+            i: 1.,
+        };
+        assert!(matches!(
+            s.try_to_point(),
+            Err(TelegrafError::BadTimestamp(_))
+        ));
+    }
+
+    #[test]
+    fn can_derive_point_ref() {
+        let s = Tags {
+            i: 1,
+            t: "t".to_string(),
+            f: 2.,
+            t2: 1.,
+        };
+        let p = s.to_point_ref().unwrap();
+        assert_eq!(p.to_point(), s.to_point());
+        assert!(matches!(p.measurement, Cow::Borrowed("Tags")));
+        assert!(matches!(p.tags[0].value, Cow::Borrowed("t")));
+
+        let s = StringField { s: "s".into() };
+        let p = s.to_point_ref().unwrap();
+        assert_eq!(p.fields[0].value, FieldDataRef::Str(Cow::Borrowed("s")));
+    }
+
+    #[test]
+    fn can_derive_point_ref_on_stack() {
+        let s = Tags {
+            i: 1,
+            t: "t".to_string(),
+            f: 2.,
+            t2: 1.,
+        };
+        let mut calls = 0;
+        s.with_point_ref(&mut |p| {
+            assert_eq!(p.to_point(), s.to_point());
+            assert!(matches!(p.tags, Cow::Borrowed(_)));
+            assert!(matches!(p.fields, Cow::Borrowed(_)));
+            calls += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(calls, 1);
+
+        let s = Optionals {
+            i: Some(1),
+            t: None,
+        };
+        s.with_point_ref(&mut |p| {
+            assert_eq!(p.to_point(), s.to_point());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn can_derive_point_ref_with_optionals() {
+        let s = Optionals {
+            i: None,
+            t: Some("t".into()),
+        };
+        assert_eq!(s.to_point_ref().unwrap().to_point(), s.to_point());
+
+        let s = OptionalTimestamp {
+            ts: Some(10),
+            i: 1.,
+        };
+        assert_eq!(s.to_point_ref().unwrap().to_point(), s.to_point());
+
+        let s = CustomMeasurementName { i: 1 };
+        assert_eq!(s.to_point_ref().unwrap().measurement, "custom");
+    }
+
+    #[test]
+    fn cannot_derive_point_ref_with_pre_epoch_time() {
+        let s = SystemTimestamp {
+            ts: UNIX_EPOCH - Duration::from_secs(1),
+            i: 1.,
+        };
+        assert!(matches!(
+            s.to_point_ref(),
+            Err(TelegrafError::BadTimestamp(_))
+        ));
+    }
 }